@@ -1,4 +1,4 @@
-use std::{collections::HashMap, fs::remove_dir_all};
+use std::{collections::HashMap, fs::remove_dir_all, path::PathBuf};
 
 use clap::Parser;
 use owo_colors::OwoColorize;
@@ -9,6 +9,7 @@ use xshell::{Shell, cmd};
 use crate::{
     config::read_config,
     error::{Result, exit},
+    publish::get_output_dir,
     repositories::{Repositories, build},
 };
 
@@ -17,19 +18,31 @@ use crate::{
 pub struct Check {
     /// The name(s) of the package repository
     repositories: Vec<Repositories>,
+
+    /// Directory in which to clone the package repositories (defaults to a unique temporary directory)
+    #[clap(long)]
+    output_dir: Option<PathBuf>,
+
+    /// Package of the cargo workspace or tool from the configuration to use
+    #[clap(short, long)]
+    package: Option<String>,
 }
 
 impl Check {
     #[instrument(name = "check", skip_all)]
     pub fn run(self) -> Result {
-        let config = read_config()?;
+        let config = read_config(self.package.as_deref())?;
 
         let repositories = build(
             &self.repositories,
             config.exclude.as_deref().unwrap_or_default(),
+            &config,
         );
 
-        let mut check_results = CheckResults::default();
+        let mut check_results = CheckResults {
+            output_dir: get_output_dir(self.output_dir.clone())?,
+            ..Default::default()
+        };
 
         let mut failed = false;
 
@@ -64,6 +77,10 @@ impl Check {
             }
         }
 
+        if self.output_dir.is_none() {
+            remove_dir_all(&check_results.output_dir).ok();
+        }
+
         if failed {
             exit(Code::FAILURE);
         }
@@ -74,6 +91,7 @@ impl Check {
 
 #[derive(Debug, Default)]
 pub struct CheckResults {
+    output_dir: PathBuf,
     current: Option<String>,
     checked: HashMap<String, (Option<String>, bool)>,
     checks_per_repo: HashMap<String, Vec<String>>,
@@ -170,7 +188,8 @@ pub fn check_repo(
         return Ok(());
     }
 
-    let dir = "/tmp/publisher-check";
+    let dir = results.output_dir.join(results.current.clone().unwrap());
+    remove_dir_all(&dir).ok();
 
     if cmd!(sh, "git clone {remote} {dir}")
         .quiet()
@@ -181,12 +200,12 @@ pub fn check_repo(
         return Ok(());
     }
 
-    sh.change_dir(dir);
+    sh.change_dir(&dir);
 
     let push_result = cmd!(sh, "git push").quiet().read_stderr();
 
     sh.change_dir("..");
-    remove_dir_all(dir)?;
+    remove_dir_all(&dir)?;
 
     if push_result.is_err() {
         results.add_result(
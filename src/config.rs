@@ -1,15 +1,22 @@
+use std::{
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
 use config::{Config, File, FileFormat};
 use eyre::eyre;
 use serde::{Deserialize, Serialize};
 use serde_json::from_str;
+use toml::{Table, Value, from_str as from_toml_str, to_string};
 use xshell::{Shell, cmd};
 
 use crate::{
     error::Result,
     repositories::{
-        aur::AurConfig, aur_bin::AurBinConfig, homebrew::HomebrewConfig, nix::NixConfig,
-        npm::NPMConfig, scoop::ScoopConfig,
+        aur::AurConfig, aur_bin::AurBinConfig, aur_git::AurGitConfig, homebrew::HomebrewConfig,
+        nix::NixConfig, npm::NPMConfig, scoop::ScoopConfig,
     },
+    targets::Target,
 };
 
 pub const CONFIG_FILE: &str = "publisher.toml";
@@ -25,14 +32,68 @@ pub struct AppConfig {
     #[serde(skip_serializing_if = "String::is_empty")]
     pub license: String,
     pub repository: String,
+    pub binaries: Option<Vec<String>>,
+    pub targets: Option<Vec<Target>>,
     pub exclude: Option<Vec<String>>,
     pub cargo: Option<String>,
+    pub dependencies: Option<DependenciesConfig>,
+    pub completions: Option<CompletionsConfig>,
+    pub manpages: Option<Vec<String>>,
+    pub service: Option<ServiceConfig>,
+    pub prerelease: Option<Prerelease>,
     pub homebrew: Option<HomebrewConfig>,
     pub aur: Option<AurConfig>,
     pub aur_bin: Option<AurBinConfig>,
+    pub aur_git: Option<AurGitConfig>,
     pub scoop: Option<ScoopConfig>,
     pub nix: Option<NixConfig>,
     pub npm: Option<NPMConfig>,
+    // Version of the cargo package, which is not configurable
+    #[serde(skip)]
+    pub cargo_version: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DependenciesConfig {
+    pub required: Option<Vec<String>>,
+    pub optional: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CompletionsConfig {
+    pub command: Option<String>,
+    pub bash: Option<String>,
+    pub zsh: Option<String>,
+    pub fish: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ServiceConfig {
+    pub args: Option<Vec<String>>,
+    pub keep_alive: Option<bool>,
+    pub working_dir: Option<String>,
+    pub log_path: Option<String>,
+    pub error_log_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Prerelease {
+    // Do not publish pre-releases to repositories without release channels
+    #[default]
+    Skip,
+    // Publish pre-releases as separate `{name}-beta` packages
+    Separate,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Build {
+    // Install the pre-built binaries from the release artifacts
+    #[default]
+    Binary,
+    // Build from the tagged source archive
+    Source,
 }
 
 #[derive(Debug, Clone, Default, Deserialize)]
@@ -42,14 +103,18 @@ pub struct CargoMetadataPackage {
     pub homepage: Option<String>,
     pub license: Option<String>,
     pub publish: Option<Vec<String>>,
+    pub version: Option<String>,
+    pub manifest_path: Option<String>,
 }
 
 #[derive(Debug, Clone, Default, Deserialize)]
 struct CargoMetadata {
     pub packages: Vec<CargoMetadataPackage>,
+    pub workspace_root: Option<String>,
 }
 
-pub fn read_cargo_config() -> Result<CargoMetadataPackage> {
+// A missing package is an error when `required`, otherwise the root package is used instead
+pub fn read_cargo_config(package: Option<&str>, required: bool) -> Result<CargoMetadataPackage> {
     let sh = Shell::new()?;
 
     // Read cargo metadata if exists
@@ -62,11 +127,77 @@ pub fn read_cargo_config() -> Result<CargoMetadataPackage> {
 
     let metadata = from_str::<CargoMetadata>(&metadata).unwrap_or_default();
 
-    Ok(metadata.packages.first().cloned().unwrap_or_default())
+    let root_manifest = metadata
+        .workspace_root
+        .as_ref()
+        .map(|root| Path::new(root).join("Cargo.toml"));
+
+    let found = package.and_then(|package| {
+        metadata
+            .packages
+            .iter()
+            .find(|p| p.name.as_deref() == Some(package))
+    });
+
+    if let Some(package) = package
+        && found.is_none()
+        && required
+    {
+        return Err(eyre!(
+            "Unable to find package {package} in the cargo workspace"
+        ));
+    }
+
+    // Otherwise, the root package of the workspace
+    let found = found.or_else(|| {
+        metadata
+            .packages
+            .iter()
+            .find(|p| p.manifest_path.as_ref().map(PathBuf::from) == root_manifest)
+            .or_else(|| metadata.packages.first())
+    });
+
+    Ok(found.cloned().unwrap_or_default())
 }
 
-pub fn read_config() -> Result<AppConfig> {
-    let package = read_cargo_config()?;
+fn read_config_file() -> Result<(Table, Vec<Table>)> {
+    let contents = read_to_string(CONFIG_FILE)
+        .map_err(|e| eyre!("Unable to read the configuration file: {e}"))?;
+
+    let mut table = from_toml_str::<Table>(&contents)
+        .map_err(|e| eyre!("Unable to parse the configuration file: {e}"))?;
+
+    let tools = match table.remove("tool") {
+        Some(Value::Array(tools)) => tools
+            .into_iter()
+            .map(|tool| match tool {
+                Value::Table(tool) => Ok(tool),
+                _ => Err(eyre!(
+                    "Unable to parse the configuration file: tool must be a table"
+                )),
+            })
+            .collect::<Result<Vec<_>>>()?,
+        Some(_) => {
+            return Err(eyre!(
+                "Unable to parse the configuration file: tool must be an array of tables"
+            ));
+        }
+        None => vec![],
+    };
+
+    Ok((table, tools))
+}
+
+fn build_config(shared: &Table, tool: Table, package: Option<&str>) -> Result<AppConfig> {
+    // Tools are not required to be cargo packages, unlike an explicitly given package
+    let package = match tool.get("name").and_then(Value::as_str) {
+        Some(tool_name) => read_cargo_config(Some(tool_name), false)?,
+        None => read_cargo_config(package, true)?,
+    };
+
+    // Settings of the tool take precedence over the shared ones
+    let mut table = shared.clone();
+    merge_tables(&mut table, tool);
 
     let builder = Config::builder()
         .set_default("name", package.name.clone())?
@@ -78,9 +209,75 @@ pub fn read_config() -> Result<AppConfig> {
             package.publish.is_none().then(|| package.name.clone()),
         )?;
 
-    Ok(builder
-        .add_source(File::new(CONFIG_FILE, FileFormat::Toml))
+    let mut config = builder
+        .add_source(File::from_str(&to_string(&table)?, FileFormat::Toml))
         .build()
         .map_err(|e| eyre!("Unable to parse the configuration file: {e}"))?
-        .try_deserialize::<AppConfig>()?)
+        .try_deserialize::<AppConfig>()?;
+
+    config.cargo_version = package.version;
+
+    Ok(config)
+}
+
+fn merge_tables(base: &mut Table, other: Table) {
+    for (key, value) in other {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(other)) => merge_tables(base, other),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn tool_names(tools: &[Table]) -> String {
+    tools
+        .iter()
+        .filter_map(|tool| tool.get("name").and_then(Value::as_str))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub fn read_config(package: Option<&str>) -> Result<AppConfig> {
+    let (shared, mut tools) = read_config_file()?;
+
+    if tools.is_empty() {
+        return build_config(&shared, Table::new(), package);
+    }
+
+    let index = match package {
+        Some(package) => tools
+            .iter()
+            .position(|tool| tool.get("name").and_then(Value::as_str) == Some(package))
+            .ok_or_else(|| {
+                eyre!(
+                    "Unable to find tool {package} in the configuration file (available: {})",
+                    tool_names(&tools)
+                )
+            })?,
+        None if tools.len() == 1 => 0,
+        None => {
+            return Err(eyre!(
+                "Multiple tools are configured, select one using --package (available: {})",
+                tool_names(&tools)
+            ));
+        }
+    };
+
+    build_config(&shared, tools.swap_remove(index), package)
+}
+
+// Reads the configuration of every tool unless a specific one is requested
+pub fn read_configs(package: Option<&str>) -> Result<Vec<AppConfig>> {
+    let (shared, tools) = read_config_file()?;
+
+    if package.is_some() || tools.len() <= 1 {
+        return Ok(vec![read_config(package)?]);
+    }
+
+    tools
+        .into_iter()
+        .map(|tool| build_config(&shared, tool, None))
+        .collect()
 }
@@ -30,7 +30,48 @@ pub struct Instructions {
 
 impl Instructions {
     #[instrument(name = "install", skip_all)]
-    pub fn run(self, info: &AppConfig) -> Result {
+    pub fn run(self, infos: &[AppConfig]) -> Result {
+        let multiple = infos.len() > 1;
+
+        let tools = infos
+            .iter()
+            .map(|info| {
+                let content = self.tool_content(info)?;
+
+                // Each tool gets its own heading when there are multiple of them
+                Ok(if multiple {
+                    format!("### {}\n\n{content}", info.name)
+                } else {
+                    content
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut file_content = read_to_string(&self.file)?;
+
+        let start_index = file_content
+            .find(&self.start_marker)
+            .ok_or(eyre!("Unable to find start marker to place instructions"))?;
+
+        let end_index = file_content
+            .find(&self.end_marker)
+            .ok_or(eyre!("Unable to find end marker to place instructions"))?;
+
+        let content = [
+            self.start_marker.clone(),
+            format!("## Install"),
+            format!(""),
+            tools.concat(),
+        ]
+        .join("\n");
+
+        file_content.replace_range(start_index..=end_index - 1, &content);
+        write(&self.file, file_content)?;
+
+        Ok(())
+    }
+
+    fn tool_content(&self, info: &AppConfig) -> Result<String> {
         let AppConfig {
             name,
             repository,
@@ -39,7 +80,7 @@ impl Instructions {
             ..
         } = info;
 
-        let mut repo_content = build(&[], &exclude.clone().unwrap_or_default())
+        let mut repo_content = build(&[], &exclude.clone().unwrap_or_default(), info)
             .into_iter()
             .map(|repo| repo.instructions(info).map(|v| v.join("\n")))
             .collect::<Result<VecDeque<_>>>()?;
@@ -62,20 +103,7 @@ impl Instructions {
             .map(|section| format!("{}{section}", self.prefix))
             .collect::<Vec<_>>();
 
-        let mut file_content = read_to_string(&self.file)?;
-
-        let start_index = file_content
-            .find(&self.start_marker)
-            .ok_or(eyre!("Unable to find start marker to place instructions"))?;
-
-        let end_index = file_content
-            .find(&self.end_marker)
-            .ok_or(eyre!("Unable to find end marker to place instructions"))?;
-
-        let content = vec![
-            self.start_marker.clone(),
-            format!("## Install"),
-            format!(""),
+        Ok([
             format!("`{name}` is available on Linux, macOS & Windows"),
             format!(""),
             content.join("\n\n"),
@@ -87,11 +115,6 @@ impl Instructions {
             format!("Download, unarchive the binary, and then put the executable in `$PATH`."),
             format!(""),
             format!(""),
-        ].join("\n");
-
-        file_content.replace_range(start_index..=end_index - 1, &content);
-        write(&self.file, file_content)?;
-
-        Ok(())
+        ].join("\n"))
     }
 }
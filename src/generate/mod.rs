@@ -4,7 +4,10 @@ use clap::Parser;
 use owo_colors::OwoColorize;
 use tracing::{info, instrument};
 
-use crate::{config::read_config, error::Result};
+use crate::{
+    config::{read_config, read_configs},
+    error::Result,
+};
 
 mod ci;
 mod instructions;
@@ -14,6 +17,10 @@ mod instructions;
 pub struct Generate {
     #[clap(subcommand)]
     cmd: Subcommands,
+
+    /// Package of the cargo workspace or tool from the configuration to use
+    #[clap(short, long, global = true)]
+    package: Option<String>,
 }
 
 #[derive(Debug, Parser)]
@@ -25,11 +32,11 @@ enum Subcommands {
 impl Generate {
     #[instrument(name = "generate", skip_all)]
     pub fn run(self) -> Result {
-        let config = read_config()?;
+        let package = self.package.as_deref();
 
         match self.cmd {
-            Subcommands::CI(x) => x.run(&config),
-            Subcommands::Instructions(x) => x.run(&config),
+            Subcommands::CI(x) => x.run(&read_config(package)?),
+            Subcommands::Instructions(x) => x.run(&read_configs(package)?),
         }
     }
 }
@@ -1,33 +1,43 @@
-use std::{error::Error as StdError, fs::write, path::Path, result::Result as StdResult};
+use std::{
+    error::Error as StdError,
+    fs::{read_to_string, write},
+    path::Path,
+    result::Result as StdResult,
+};
 
 use clap::{Parser, ValueEnum};
+use eyre::eyre;
 use inquire::{
     MultiSelect, Text, required,
     validator::{ErrorMessage, Validation},
 };
 use regex::Regex;
-use toml::to_string;
+use toml::{Table, Value, from_str, to_string};
 use tracing::instrument;
 
 use crate::{
     config::{AppConfig, CONFIG_FILE, read_cargo_config},
     error::Result,
     repositories::{
-        Repositories, aur::AurConfig, aur_bin::AurBinConfig, homebrew::HomebrewConfig,
-        nix::NixConfig, npm::NPMConfig, scoop::ScoopConfig,
+        Repositories, aur::AurConfig, aur_bin::AurBinConfig, aur_git::AurGitConfig,
+        homebrew::HomebrewConfig, nix::NixConfig, npm::NPMConfig, scoop::ScoopConfig,
     },
 };
 
-type ValidatorResult = StdResult<Validation, Box<(dyn StdError + Send + Sync + 'static)>>;
+type ValidatorResult = StdResult<Validation, Box<dyn StdError + Send + Sync + 'static>>;
 
 /// Setup configuration for publishing to package repositories
 #[derive(Debug, Parser)]
-pub struct Init {}
+pub struct Init {
+    /// Package of the cargo workspace to add as a tool to the configuration
+    #[clap(short, long)]
+    package: Option<String>,
+}
 
 impl Init {
     #[instrument(name = "init", skip_all)]
     pub fn run(self) -> Result {
-        let package = read_cargo_config()?;
+        let package = read_cargo_config(self.package.as_deref(), true)?;
 
         let name = Text::new("Name of the binary?")
             .with_initial_value(&package.name.clone().unwrap_or_default())
@@ -56,11 +66,19 @@ impl Init {
             .with_validator(repo_uri_validator)
             .prompt()?;
 
+        // AUR (git) is opt-in since it creates a separate package
+        let default_repositories = Repositories::value_variants()
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| **r != Repositories::AurGit)
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+
         let package_repositories = MultiSelect::new(
             "Package repositories to publish to?",
             Repositories::value_variants().into(),
         )
-        .with_all_selected_by_default()
+        .with_default(&default_repositories)
         .prompt()?;
 
         let homebrew = if package_repositories.contains(&Repositories::Homebrew) {
@@ -81,6 +99,7 @@ impl Init {
             (different_name || different_repo).then_some(HomebrewConfig {
                 name: different_name.then_some(homebrew_name),
                 repository: different_repo.then_some(homebrew_repository),
+                ..Default::default()
             })
         } else {
             None
@@ -96,7 +115,7 @@ impl Init {
 
             different_name.then_some(AurConfig {
                 name: Some(aur_name),
-                conflicts: None,
+                ..Default::default()
             })
         } else {
             None
@@ -114,7 +133,26 @@ impl Init {
 
             different_name.then_some(AurBinConfig {
                 name: Some(aur_bin_name),
-                conflicts: None,
+                ..Default::default()
+            })
+        } else {
+            None
+        };
+
+        let aur_git = if package_repositories.contains(&Repositories::AurGit) {
+            let package_name = format!("{name}-git");
+
+            let aur_git_name = Text::new("AUR (git) package name?")
+                .with_initial_value(&package_name)
+                .with_validator(required!())
+                .prompt()?;
+
+            let different_name = aur_git_name != package_name;
+
+            // Always written so that it is selected by default when publishing
+            Some(AurGitConfig {
+                name: different_name.then_some(aur_git_name),
+                ..Default::default()
             })
         } else {
             None
@@ -138,6 +176,7 @@ impl Init {
             (different_name || different_repo).then_some(ScoopConfig {
                 name: different_name.then_some(scoop_name),
                 repository: different_repo.then_some(scoop_repository),
+                ..Default::default()
             })
         } else {
             None
@@ -161,8 +200,7 @@ impl Init {
             (different_name || different_repo).then_some(NixConfig {
                 name: different_name.then_some(nix_name),
                 repository: different_repo.then_some(nix_repository),
-                path: None,
-                lockfile: None,
+                ..Default::default()
             })
         } else {
             None
@@ -178,6 +216,7 @@ impl Init {
 
             different_name.then_some(NPMConfig {
                 name: Some(npm_name),
+                ..Default::default()
             })
         } else {
             None
@@ -211,17 +250,44 @@ impl Init {
                 license
             },
             repository,
+            binaries: None,
+            targets: None,
             exclude: (!exclude.is_empty()).then_some(exclude),
             cargo: None,
+            dependencies: None,
+            completions: None,
+            manpages: None,
+            service: None,
+            prerelease: None,
             homebrew,
             aur,
             aur_bin,
+            aur_git,
             scoop,
             nix,
             npm,
+            cargo_version: None,
         };
 
-        write(Path::new(CONFIG_FILE), to_string(&config)?)?;
+        if self.package.is_some() {
+            // Add the tool to the existing configuration of the workspace
+            let mut table = read_to_string(CONFIG_FILE)
+                .ok()
+                .map(|contents| from_str::<Table>(&contents))
+                .transpose()?
+                .unwrap_or_default();
+
+            table
+                .entry("tool")
+                .or_insert_with(|| Value::Array(vec![]))
+                .as_array_mut()
+                .ok_or(eyre!("Unable to add the tool to the configuration file"))?
+                .push(Value::try_from(&config)?);
+
+            write(Path::new(CONFIG_FILE), to_string(&table)?)?;
+        } else {
+            write(Path::new(CONFIG_FILE), to_string(&config)?)?;
+        }
 
         Ok(())
     }
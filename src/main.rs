@@ -14,6 +14,7 @@ mod styles;
 mod config;
 mod repositories;
 mod targets;
+mod version;
 
 mod check;
 mod generate;
@@ -1,25 +1,28 @@
 use std::{
     fmt::Debug,
     fs::{create_dir_all, remove_dir_all, remove_file, write},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
-use clap::Parser;
+use anstream::println;
+use clap::{Parser, ValueEnum, value_parser};
 use owo_colors::OwoColorize;
+use tempfile::{Builder, TempDir};
 use tracing::{info, instrument, warn};
 use xshell::{Shell, cmd};
 
 use crate::{
     config::read_config,
     error::Result,
-    repositories::{Repositories, Repository, build, update_config},
+    repositories::{Repositories, Repository, build, update_config, update_prerelease},
+    version::{infer_version, is_prerelease, validate_version},
 };
 
 /// Publish the tool to package repositories
 #[derive(Debug, Parser)]
 pub struct Publish {
-    /// Version to publish
-    version: String,
+    /// Version to publish (defaults to the version in `Cargo.toml` or the tag at HEAD)
+    version: Option<String>,
 
     /// The name(s) of the package repository
     repositories: Vec<Repositories>,
@@ -27,22 +30,83 @@ pub struct Publish {
     /// Disable dry run mode
     #[clap(long)]
     no_dry_run: bool,
+
+    /// Release number of the AUR packages (defaults to incrementing it for the same version)
+    #[clap(long, value_parser = value_parser!(u32).range(1..))]
+    pkgrel: Option<u32>,
+
+    /// Directory in which to prepare the packages (defaults to a unique temporary directory)
+    #[clap(long)]
+    output_dir: Option<PathBuf>,
+
+    /// Package of the cargo workspace or tool from the configuration to use
+    #[clap(short, long)]
+    package: Option<String>,
+
+    /// One-time password for publishing to NPM with two-factor authentication
+    #[clap(long)]
+    otp: Option<String>,
+
+    /// Publish to NPM with provenance statements, which needs to be run in a supported CI
+    #[clap(long)]
+    provenance: bool,
+
+    /// Do not ask for confirmation of the inferred version
+    #[clap(short, long)]
+    yes: bool,
 }
 
 impl Publish {
     #[instrument(name = "publish", skip_all)]
-    pub fn run(self) -> Result {
-        let mut config = read_config()?;
-        let exclude = config.exclude.clone().unwrap_or_default();
+    pub fn run(mut self) -> Result {
+        // Since the version is optional, the first repository can end up in its place
+        if let Some(repository) = self
+            .version
+            .as_deref()
+            .and_then(|v| Repositories::from_str(v, true).ok())
+        {
+            self.repositories.insert(0, repository);
+            self.version = None;
+        }
+
+        let mut config = read_config(self.package.as_deref())?;
+        let mut exclude = config.exclude.clone().unwrap_or_default();
+
+        let version = match self.version {
+            Some(version) => version,
+            None => infer_version(&config, self.yes)?,
+        };
+
+        if is_prerelease(&version) {
+            update_prerelease(&self.repositories, &mut exclude, &mut config);
+        }
 
         // We need to update config depending on what user has provided
         update_config(&self.repositories, &exclude, &mut config);
 
-        let repositories = build(&self.repositories, &exclude);
+        let options = PublishOptions {
+            pkgrel: self.pkgrel,
+            otp: self.otp,
+            provenance: self.provenance,
+        };
+
+        let repositories = build(&self.repositories, &exclude, &config);
+
+        validate_version(&config, &version, &repositories)?;
+
+        // The temporary directory is removed when dropped, including when publishing fails
+        let temp_dir = match self.output_dir {
+            Some(_) => None,
+            None => Some(create_temp_dir()?),
+        };
+        let output_dir = match &temp_dir {
+            Some(temp_dir) => temp_dir.path().to_path_buf(),
+            None => get_output_dir(self.output_dir)?,
+        };
 
         for repository in repositories {
             info!("{}", repository.name().blue());
-            repository.publish(&config, &self.version, !self.no_dry_run)?;
+            repository.publish(&config, &version, !self.no_dry_run, &output_dir, &options)?;
         }
 
         if !self.no_dry_run {
@@ -50,17 +114,41 @@ impl Publish {
                 "{}",
                 "Not publishing because dry run mode is enabled".yellow()
             );
+            info!("Generated files are in {}", output_dir.display().cyan());
+            temp_dir.map(TempDir::keep);
         }
 
         Ok(())
     }
 }
 
-pub fn prepare_tmp_dir(repository: &dyn Repository) -> Result<(Shell, String)> {
+/// Flags of the publish command that are only used by some package repositories
+#[derive(Debug)]
+pub struct PublishOptions {
+    pub pkgrel: Option<u32>,
+    pub otp: Option<String>,
+    pub provenance: bool,
+}
+
+pub fn get_output_dir(output_dir: Option<PathBuf>) -> Result<PathBuf> {
+    if let Some(output_dir) = output_dir {
+        create_dir_all(&output_dir)?;
+        return Ok(output_dir);
+    }
+
+    Ok(create_temp_dir()?.keep())
+}
+
+// Unique per run so that concurrent runs do not clobber each other
+fn create_temp_dir() -> Result<TempDir> {
+    Ok(Builder::new().prefix("publisher-").tempdir()?)
+}
+
+pub fn prepare_tmp_dir(repository: &dyn Repository, output_dir: &Path) -> Result<(Shell, String)> {
     let id = repository.name();
 
     let sh = Shell::new()?;
-    let dir = format!("/tmp/publisher/{id}");
+    let dir = output_dir.join(id).to_string_lossy().to_string();
 
     remove_dir_all(&dir).ok();
     create_dir_all(&dir)?;
@@ -70,8 +158,12 @@ pub fn prepare_tmp_dir(repository: &dyn Repository) -> Result<(Shell, String)> {
     Ok((sh, dir))
 }
 
-pub fn prepare_git_repo(repository: &dyn Repository, remote: &str) -> Result<(Shell, String)> {
-    let (sh, dir) = prepare_tmp_dir(repository)?;
+pub fn prepare_git_repo(
+    repository: &dyn Repository,
+    output_dir: &Path,
+    remote: &str,
+) -> Result<(Shell, String)> {
+    let (sh, dir) = prepare_tmp_dir(repository, output_dir)?;
 
     cmd!(sh, "git init").quiet().ignore_stdout().run()?;
     cmd!(sh, "git remote add origin {remote}")
@@ -147,7 +239,7 @@ where
 
     info!("  {:>11} {}", "downloading".magenta(), path.cyan());
 
-    cmd!(sh, "curl -L {archive_url} -o {archive_path}")
+    cmd!(sh, "curl -fL {archive_url} -o {archive_path}")
         .quiet()
         .ignore_stdout()
         .ignore_stderr()
@@ -177,3 +269,18 @@ pub fn commit_and_push(sh: &Shell, name: &str, version: &str) -> Result {
 
     Ok(())
 }
+
+pub fn print_diff(sh: &Shell) -> Result {
+    // Staged changes are the difference between the remote and generated files
+    let diff = cmd!(sh, "git diff --cached --no-ext-diff --color=always")
+        .quiet()
+        .read()?;
+
+    if diff.is_empty() {
+        info!("  {:>11}", "unchanged".magenta());
+    } else {
+        println!("{diff}");
+    }
+
+    Ok(())
+}
@@ -1,12 +1,20 @@
+use std::path::Path;
+
 use serde::{Deserialize, Serialize};
 use xshell::{Shell, cmd};
 
-use super::get_checksums;
+use super::{
+    get_checksums, get_dependencies,
+    pkgbuild::{
+        Pkgbuild, Source, check_pkgbuild, get_pkgrel, get_pkgver, install_binaries,
+        install_service, install_shell_files,
+    },
+};
 use crate::{
     check::{CheckResults, check_git, check_repo},
-    config::AppConfig,
+    config::{AppConfig, DependenciesConfig},
     error::Result,
-    publish::{commit_and_push, prepare_git_repo, write_and_add},
+    publish::{PublishOptions, commit_and_push, prepare_git_repo, print_diff, write_and_add},
     repositories::Repository,
     targets::Target,
 };
@@ -15,6 +23,7 @@ use crate::{
 pub struct AurConfig {
     pub name: Option<String>,
     pub conflicts: Option<Vec<String>>,
+    pub dependencies: Option<DependenciesConfig>,
 }
 
 #[derive(Debug, Clone)]
@@ -55,10 +64,23 @@ impl Repository for Aur {
             true,
         )?;
 
+        check_pkgbuild(results, &name)?;
+
         Ok(())
     }
 
-    fn publish(&self, info: &AppConfig, version: &str, dry_run: bool) -> Result {
+    fn targets(&self, _: &AppConfig) -> Vec<Target> {
+        vec![Target::Source]
+    }
+
+    fn publish(
+        &self,
+        info: &AppConfig,
+        version: &str,
+        dry_run: bool,
+        output_dir: &Path,
+        options: &PublishOptions,
+    ) -> Result {
         let AppConfig {
             name: cli_name,
             description,
@@ -69,11 +91,17 @@ impl Repository for Aur {
         } = info;
 
         let name = get_name(info);
-        let (sh, dir) = prepare_git_repo(self, &format!("ssh://aur@aur.archlinux.org/{name}.git"))?;
+        let (sh, dir) = prepare_git_repo(
+            self,
+            output_dir,
+            &format!("ssh://aur@aur.archlinux.org/{name}.git"),
+        )?;
 
         let github_repo_name = repository.split('/').next_back().unwrap();
 
-        let checksums = get_checksums(info, version, vec![Target::Source])?;
+        let checksums = get_checksums(info, version, self.targets(info))?;
+
+        let pkgrel = get_pkgrel(&dir, version, options.pkgrel)?;
 
         let conflicts = info
             .aur
@@ -81,75 +109,63 @@ impl Repository for Aur {
             .and_then(|info| info.conflicts.clone())
             .unwrap_or_default();
 
-        let conflicts_pkgbuild = conflicts
-            .iter()
-            .map(|c| format!("{c:?}"))
-            .collect::<Vec<String>>()
-            .join(" ");
-
-        let conflicts_srcinfo = conflicts
-            .iter()
-            .map(|c| format!("\tconflicts = {c}"))
-            .collect::<Vec<String>>()
-            .join("\n");
-
-        write_and_add(&sh, &dir, "PKGBUILD", || {
-            vec![
-                format!("pkgname={name}"),
-                format!("pkgver={version}"),
-                format!("pkgrel=0"),
-                format!("pkgdesc={description:?}"),
-                format!("arch=('x86_64' 'i686')"),
-                format!("url={homepage:?}"),
-                format!("license=({license:?})"),
-                format!("makedepends=('cargo')"),
-                format!("provides=({cli_name:?})"),
-                format!("conflicts=({conflicts_pkgbuild})"),
-                format!(
-                    "source=($pkgname-$pkgver.zip::https://github.com/{repository}/archive/refs/tags/v$pkgver.zip)"
-                ),
-                format!("sha256sums=({:?})", checksums.get(&Target::Source).unwrap()),
-                format!(""),
-                format!("build() {{"),
-                format!("    cd \"$srcdir/{github_repo_name}-$pkgver\""),
-                format!("    cargo build --release --locked"),
-                format!("}}"),
-                format!(""),
-                format!("package() {{"),
-                format!("    cd \"$srcdir/{github_repo_name}-$pkgver\""),
-                format!(
-                    "    install -Dm755 \"target/release/{cli_name}\" \"$pkgdir/usr/bin/{cli_name}\""
+        let (depends, optdepends) = get_dependencies(
+            info,
+            info.aur.as_ref().and_then(|aur| aur.dependencies.as_ref()),
+        );
+
+        let pkgbuild = Pkgbuild {
+            pkgname: name.clone(),
+            pkgver: get_pkgver(version),
+            pkgrel,
+            pkgdesc: description.clone(),
+            arch: vec!["x86_64".into(), "i686".into()],
+            url: homepage.clone(),
+            license: vec![license.clone()],
+            makedepends: vec!["cargo".into()],
+            depends,
+            optdepends,
+            provides: vec![cli_name.clone()],
+            conflicts,
+            sources: vec![Source {
+                arch: None,
+                source: format!(
+                    "{name}-{version}.zip::https://github.com/{repository}/archive/refs/tags/v{version}.zip"
                 ),
-                format!(
-                    "    install -Dm644 \"LICENSE\" \"$pkgdir/usr/share/licenses/{cli_name}/LICENSE\""
+                sha256sum: checksums.get(&Target::Source).unwrap().clone(),
+            }],
+            functions: vec![
+                (
+                    format!("build"),
+                    vec![
+                        format!("    cd \"$srcdir/{github_repo_name}-{version}\""),
+                        format!("    cargo build --release --locked"),
+                    ],
                 ),
-                format!("}}"),
-            ]
-        })?;
-
-        write_and_add(&sh, &dir, ".SRCINFO", || {
-            vec![
-                format!("pkgbase = {name}"),
-                format!("\tpkgver = {version}"),
-                format!("\tpkgrel = 0"),
-                format!("\tpkgdesc = {description}"),
-                format!("\turl = {homepage}"),
-                format!("\tarch = x86_64"),
-                format!("\tarch = i686"),
-                format!("\tlicense = {license}"),
-                format!("\tmakedepends = cargo"),
-                format!("\tprovides = {cli_name}"),
-                conflicts_srcinfo,
-                format!(
-                    "\tsource = {name}-{version}.zip::https://github.com/{repository}/archive/refs/tags/v{version}.zip"
+                (
+                    format!("package"),
+                    [
+                        vec![format!("    cd \"$srcdir/{github_repo_name}-{version}\"")],
+                        install_binaries(info, "target/release/"),
+                        vec![
+                            format!(
+                                "    install -Dm644 \"LICENSE\" \"$pkgdir/usr/share/licenses/{cli_name}/LICENSE\""
+                            ),
+                        ],
+                        install_shell_files(info),
+                        install_service(info),
+                    ]
+                    .concat(),
                 ),
-                format!("\tsha256sums = {}", checksums.get(&Target::Source).unwrap()),
-                format!(""),
-                format!("pkgname = {name}"),
-            ]
-        })?;
+            ],
+        };
+
+        write_and_add(&sh, &dir, "PKGBUILD", || pkgbuild.to_pkgbuild())?;
+        write_and_add(&sh, &dir, ".SRCINFO", || pkgbuild.to_srcinfo())?;
 
-        if !dry_run {
+        if dry_run {
+            print_diff(&sh)?;
+        } else {
             commit_and_push(&sh, &name, version)?;
         }
 
@@ -1,12 +1,21 @@
+use std::path::Path;
+
+use eyre::eyre;
 use serde::{Deserialize, Serialize};
 use xshell::{Shell, cmd};
 
-use super::get_checksums;
+use super::{
+    get_checksums, get_dependencies, get_targets,
+    pkgbuild::{
+        Pkgbuild, Source, check_pkgbuild, get_pkgrel, get_pkgver, install_binaries,
+        install_service, install_shell_files,
+    },
+};
 use crate::{
     check::{CheckResults, check_git, check_repo},
-    config::AppConfig,
+    config::{AppConfig, DependenciesConfig},
     error::Result,
-    publish::{commit_and_push, prepare_git_repo, write_and_add},
+    publish::{PublishOptions, commit_and_push, prepare_git_repo, print_diff, write_and_add},
     repositories::Repository,
     targets::Target,
 };
@@ -15,6 +24,7 @@ use crate::{
 pub struct AurBinConfig {
     pub name: Option<String>,
     pub conflicts: Option<Vec<String>>,
+    pub dependencies: Option<DependenciesConfig>,
 }
 
 #[derive(Debug, Clone)]
@@ -55,10 +65,26 @@ impl Repository for AurBin {
             true,
         )?;
 
+        check_pkgbuild(results, &name)?;
+
         Ok(())
     }
 
-    fn publish(&self, info: &AppConfig, version: &str, dry_run: bool) -> Result {
+    fn targets(&self, info: &AppConfig) -> Vec<Target> {
+        get_arches(info)
+            .into_iter()
+            .map(|(target, _)| target)
+            .collect()
+    }
+
+    fn publish(
+        &self,
+        info: &AppConfig,
+        version: &str,
+        dry_run: bool,
+        output_dir: &Path,
+        options: &PublishOptions,
+    ) -> Result {
         let AppConfig {
             name: cli_name,
             description,
@@ -69,100 +95,80 @@ impl Repository for AurBin {
         } = info;
 
         let name = get_name(info);
-        let (sh, dir) = prepare_git_repo(self, &format!("ssh://aur@aur.archlinux.org/{name}.git"))?;
-
-        let checksums = get_checksums(
-            info,
-            version,
-            vec![Target::X86_64UnknownLinuxGnu, Target::I686UnknownLinuxGnu],
+        let (sh, dir) = prepare_git_repo(
+            self,
+            output_dir,
+            &format!("ssh://aur@aur.archlinux.org/{name}.git"),
         )?;
 
+        let arches = get_arches(info);
+
+        if arches.is_empty() {
+            return Err(eyre!("No linux gnu targets are configured for AUR (bin)"));
+        }
+
+        let checksums = get_checksums(info, version, self.targets(info))?;
+
+        let pkgrel = get_pkgrel(&dir, version, options.pkgrel)?;
+
         let conflicts = info
             .aur_bin
             .as_ref()
             .and_then(|info| info.conflicts.clone())
             .unwrap_or_default();
 
-        let conflicts_pkgbuild = conflicts
-            .iter()
-            .map(|c| format!("{c:?}"))
-            .collect::<Vec<String>>()
-            .join(" ");
-
-        let conflicts_srcinfo = conflicts
-            .iter()
-            .map(|c| format!("\tconflicts = {c}"))
-            .collect::<Vec<String>>()
-            .join("\n");
-
-        write_and_add(&sh, &dir, "PKGBUILD", || {
-            vec![
-                format!("pkgname={name}"),
-                format!("pkgver={version}"),
-                format!("pkgrel=0"),
-                format!("pkgdesc={description:?}"),
-                format!("arch=('x86_64' 'i686')"),
-                format!("url={homepage:?}"),
-                format!("license=({license:?})"),
-                format!("provides=({cli_name:?})"),
-                format!("conflicts=({conflicts_pkgbuild})"),
-                format!(
-                    "source_x86_64=($pkgname-$pkgver.zip::https://github.com/{repository}/releases/download/v$pkgver/{cli_name}-v$pkgver-x86_64-unknown-linux-gnu.zip)"
-                ),
-                format!(
-                    "sha256sums_x86_64=({:?})",
-                    checksums.get(&Target::X86_64UnknownLinuxGnu).unwrap()
-                ),
-                format!(
-                    "source_i686=($pkgname-$pkgver.zip::https://github.com/{repository}/releases/download/v$pkgver/{cli_name}-v$pkgver-i686-unknown-linux-gnu.zip)"
-                ),
-                format!(
-                    "sha256sums_i686=({:?})",
-                    checksums.get(&Target::I686UnknownLinuxGnu).unwrap()
-                ),
-                format!(""),
-                format!("package() {{"),
-                format!("    cd \"$srcdir\""),
-                format!("    install -Dm755 \"{cli_name}\" \"$pkgdir/usr/bin/{cli_name}\""),
-                format!(
-                    "    install -Dm644 \"LICENSE\" \"$pkgdir/usr/share/licenses/{cli_name}/LICENSE\""
-                ),
-                format!("}}"),
-            ]
-        })?;
-
-        write_and_add(&sh, &dir, ".SRCINFO", || {
-            vec![
-                format!("pkgbase = {name}"),
-                format!("\tpkgver = {version}"),
-                format!("\tpkgrel = 0"),
-                format!("\tpkgdesc = {description}"),
-                format!("\turl = {homepage}"),
-                format!("\tarch = x86_64"),
-                format!("\tarch = i686"),
-                format!("\tlicense = {license}"),
-                format!("\tprovides = {cli_name}"),
-                conflicts_srcinfo,
-                format!(
-                    "\tsource_x86_64 = {name}-{version}.zip::https://github.com/{repository}/releases/download/v{version}/{cli_name}-v{version}-x86_64-unknown-linux-gnu.zip"
-                ),
-                format!(
-                    "\tsha256sums_x86_64 = {}",
-                    checksums.get(&Target::X86_64UnknownLinuxGnu).unwrap()
-                ),
-                format!(
-                    "\tsource_i686 = {name}-{version}.zip::https://github.com/{repository}/releases/download/v{version}/{cli_name}-v{version}-i686-unknown-linux-gnu.zip"
-                ),
-                format!(
-                    "\tsha256sums_i686 = {}",
-                    checksums.get(&Target::I686UnknownLinuxGnu).unwrap()
-                ),
-                format!(""),
-                format!("pkgname = {name}"),
-            ]
-        })?;
-
-        if !dry_run {
+        let source = |(target, arch): &(Target, &str)| Source {
+            arch: Some(arch.to_string()),
+            source: format!(
+                "{name}-{version}.zip::https://github.com/{repository}/releases/download/v{version}/{cli_name}-v{version}-{target}.zip"
+            ),
+            sha256sum: checksums.get(target).unwrap().clone(),
+        };
+
+        let (depends, optdepends) = get_dependencies(
+            info,
+            info.aur_bin
+                .as_ref()
+                .and_then(|aur_bin| aur_bin.dependencies.as_ref()),
+        );
+
+        let pkgbuild = Pkgbuild {
+            pkgname: name.clone(),
+            pkgver: get_pkgver(version),
+            pkgrel,
+            pkgdesc: description.clone(),
+            arch: arches.iter().map(|(_, arch)| arch.to_string()).collect(),
+            url: homepage.clone(),
+            license: vec![license.clone()],
+            depends,
+            optdepends,
+            provides: vec![cli_name.clone()],
+            conflicts,
+            sources: arches.iter().map(source).collect(),
+            functions: vec![(
+                format!("package"),
+                [
+                    vec![format!("    cd \"$srcdir\"")],
+                    install_binaries(info, ""),
+                    vec![
+                        format!(
+                            "    install -Dm644 \"LICENSE\" \"$pkgdir/usr/share/licenses/{cli_name}/LICENSE\""
+                        ),
+                    ],
+                    install_shell_files(info),
+                    install_service(info),
+                ]
+                .concat(),
+            )],
+            ..Default::default()
+        };
+
+        write_and_add(&sh, &dir, "PKGBUILD", || pkgbuild.to_pkgbuild())?;
+        write_and_add(&sh, &dir, ".SRCINFO", || pkgbuild.to_srcinfo())?;
+
+        if dry_run {
+            print_diff(&sh)?;
+        } else {
             commit_and_push(&sh, &name, version)?;
         }
 
@@ -188,3 +194,17 @@ pub(super) fn get_name(info: &AppConfig) -> String {
         .and_then(|aur_bin| aur_bin.name.clone())
         .unwrap_or_else(|| format!("{}-bin", info.name))
 }
+
+// Architectures of the configured targets that have binaries for Arch Linux
+fn get_arches(info: &AppConfig) -> Vec<(Target, &'static str)> {
+    let targets = get_targets(info);
+
+    [
+        (Target::X86_64UnknownLinuxGnu, "x86_64"),
+        (Target::I686UnknownLinuxGnu, "i686"),
+        (Target::Aarch64UnknownLinuxGnu, "aarch64"),
+    ]
+    .into_iter()
+    .filter(|(target, _)| targets.contains(target))
+    .collect()
+}
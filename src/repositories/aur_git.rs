@@ -0,0 +1,208 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use xshell::{Shell, cmd};
+
+use super::{
+    get_dependencies,
+    pkgbuild::{
+        Pkgbuild, Source, check_pkgbuild, get_pkgrel, get_pkgver, install_binaries,
+        install_service, install_shell_files,
+    },
+};
+use crate::{
+    check::{CheckResults, check_git, check_repo},
+    config::{AppConfig, DependenciesConfig},
+    error::Result,
+    publish::{PublishOptions, commit_and_push, prepare_git_repo, print_diff, write_and_add},
+    repositories::Repository,
+    targets::Target,
+};
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct AurGitConfig {
+    pub name: Option<String>,
+    pub conflicts: Option<Vec<String>>,
+    pub dependencies: Option<DependenciesConfig>,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct AurGit;
+
+impl Repository for AurGit {
+    fn name(&self) -> &'static str {
+        "AUR (git)"
+    }
+
+    fn check(&self, results: &mut CheckResults, info: &AppConfig) -> Result {
+        let sh = Shell::new()?;
+
+        check_git(&sh, results);
+
+        let ssh_configured = cmd!(sh, "ssh aur@aur.archlinux.org")
+            .quiet()
+            .ignore_status()
+            .read_stderr()?
+            .contains("Interactive shell is disabled.");
+
+        results.add_result(
+            "ssh",
+            (!ssh_configured).then_some("AUR SSH access is not configured"),
+        );
+
+        let name = get_name(info);
+
+        check_repo(
+            &sh,
+            &if ssh_configured {
+                format!("ssh://aur@aur.archlinux.org/{name}.git")
+            } else {
+                format!("https://aur.archlinux.org/{name}.git")
+            },
+            "master",
+            results,
+            true,
+        )?;
+
+        check_pkgbuild(results, &name)?;
+
+        Ok(())
+    }
+
+    fn targets(&self, _: &AppConfig) -> Vec<Target> {
+        // Built from the default branch, so no release artifacts are needed
+        vec![]
+    }
+
+    fn publish(
+        &self,
+        info: &AppConfig,
+        version: &str,
+        dry_run: bool,
+        output_dir: &Path,
+        options: &PublishOptions,
+    ) -> Result {
+        let AppConfig {
+            name: cli_name,
+            description,
+            homepage,
+            license,
+            repository,
+            ..
+        } = info;
+
+        let name = get_name(info);
+        let (sh, dir) = prepare_git_repo(
+            self,
+            output_dir,
+            &format!("ssh://aur@aur.archlinux.org/{name}.git"),
+        )?;
+
+        let pkgrel = get_pkgrel(&dir, version, options.pkgrel)?;
+
+        let conflicts = info
+            .aur_git
+            .as_ref()
+            .and_then(|info| info.conflicts.clone())
+            .unwrap_or_default();
+
+        let (depends, optdepends) = get_dependencies(
+            info,
+            info.aur_git
+                .as_ref()
+                .and_then(|aur_git| aur_git.dependencies.as_ref()),
+        );
+
+        let pkgbuild = Pkgbuild {
+            pkgname: name.clone(),
+            pkgver: get_pkgver(version),
+            pkgrel,
+            pkgdesc: description.clone(),
+            arch: vec!["x86_64".into(), "i686".into()],
+            url: homepage.clone(),
+            license: vec![license.clone()],
+            makedepends: vec!["cargo".into(), "git".into()],
+            depends,
+            optdepends,
+            provides: vec![cli_name.clone()],
+            conflicts,
+            sources: vec![Source {
+                arch: None,
+                source: format!("{name}::git+https://github.com/{repository}.git"),
+                sha256sum: "SKIP".into(),
+            }],
+            functions: vec![
+                (
+                    format!("pkgver"),
+                    vec![
+                        format!("    cd \"$srcdir/{name}\""),
+                        format!(
+                            "    git describe --long --tags --abbrev=7 | sed 's/^v//;s/\\([^-]*-g\\)/r\\1/;s/-/./g'"
+                        ),
+                    ],
+                ),
+                (
+                    format!("prepare"),
+                    vec![
+                        format!("    cd \"$srcdir/{name}\""),
+                        format!(
+                            "    cargo fetch --locked --target \"$(rustc -vV | sed -n 's/host: //p')\""
+                        ),
+                    ],
+                ),
+                (
+                    format!("build"),
+                    vec![
+                        format!("    cd \"$srcdir/{name}\""),
+                        format!("    cargo build --frozen --release"),
+                    ],
+                ),
+                (
+                    format!("package"),
+                    [
+                        vec![format!("    cd \"$srcdir/{name}\"")],
+                        install_binaries(info, "target/release/"),
+                        vec![
+                            format!(
+                                "    install -Dm644 \"LICENSE\" \"$pkgdir/usr/share/licenses/{cli_name}/LICENSE\""
+                            ),
+                        ],
+                        install_shell_files(info),
+                        install_service(info),
+                    ]
+                    .concat(),
+                ),
+            ],
+        };
+
+        write_and_add(&sh, &dir, "PKGBUILD", || pkgbuild.to_pkgbuild())?;
+        write_and_add(&sh, &dir, ".SRCINFO", || pkgbuild.to_srcinfo())?;
+
+        if dry_run {
+            print_diff(&sh)?;
+        } else {
+            commit_and_push(&sh, &name, version)?;
+        }
+
+        Ok(())
+    }
+
+    fn instructions(&self, info: &AppConfig) -> Result<Vec<String>> {
+        let name = get_name(info);
+
+        Ok(vec![
+            format!("With [AUR (git)](https://aur.archlinux.org)"),
+            format!(""),
+            format!("```"),
+            format!("yay -S {name}"),
+            format!("```"),
+        ])
+    }
+}
+
+pub(super) fn get_name(info: &AppConfig) -> String {
+    info.aur_git
+        .as_ref()
+        .and_then(|aur_git| aur_git.name.clone())
+        .unwrap_or_else(|| format!("{}-git", info.name))
+}
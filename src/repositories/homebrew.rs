@@ -1,13 +1,18 @@
+use std::path::Path;
+
 use heck::ToUpperCamelCase;
 use serde::{Deserialize, Serialize};
 use xshell::Shell;
 
-use super::get_checksums;
+use super::{
+    Completion, get_binaries, get_checksums, get_completions, get_dependencies, get_manpages,
+    get_service, get_targets,
+};
 use crate::{
     check::{CheckResults, check_git, check_repo},
-    config::AppConfig,
+    config::{AppConfig, Build, DependenciesConfig, ServiceConfig},
     error::Result,
-    publish::{commit_and_push, prepare_git_repo, write_and_add},
+    publish::{PublishOptions, commit_and_push, prepare_git_repo, print_diff, write_and_add},
     repositories::Repository,
     targets::Target,
 };
@@ -16,6 +21,26 @@ use crate::{
 pub struct HomebrewConfig {
     pub name: Option<String>,
     pub repository: Option<String>,
+    pub dependencies: Option<DependenciesConfig>,
+    pub test: Option<HomebrewTestConfig>,
+    pub livecheck: Option<HomebrewLivecheckConfig>,
+    pub caveats: Option<String>,
+    pub conflicts_with: Option<Vec<String>>,
+    pub build: Option<Build>,
+    pub service: Option<ServiceConfig>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct HomebrewTestConfig {
+    pub command: Option<String>,
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct HomebrewLivecheckConfig {
+    pub url: Option<String>,
+    pub strategy: Option<String>,
+    pub regex: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -44,7 +69,33 @@ impl Repository for Homebrew {
         Ok(())
     }
 
-    fn publish(&self, info: &AppConfig, version: &str, dry_run: bool) -> Result {
+    fn targets(&self, info: &AppConfig) -> Vec<Target> {
+        if get_build(info) == Build::Source {
+            return vec![Target::Source];
+        }
+
+        get_targets(info)
+            .into_iter()
+            .filter(|target| {
+                matches!(
+                    target,
+                    Target::Aarch64AppleDarwin
+                        | Target::X86_64AppleDarwin
+                        | Target::Aarch64UnknownLinuxGnu
+                        | Target::X86_64UnknownLinuxGnu
+                )
+            })
+            .collect()
+    }
+
+    fn publish(
+        &self,
+        info: &AppConfig,
+        version: &str,
+        dry_run: bool,
+        output_dir: &Path,
+        _: &PublishOptions,
+    ) -> Result {
         let AppConfig {
             name: cli_name,
             description,
@@ -56,66 +107,245 @@ impl Repository for Homebrew {
 
         let name = get_name(info);
         let pkg_repo = get_repository(info);
-        let (sh, dir) = prepare_git_repo(self, &format!("git@github.com:{pkg_repo}"))?;
+        let (sh, dir) = prepare_git_repo(self, output_dir, &format!("git@github.com:{pkg_repo}"))?;
+
+        let checksums = get_checksums(info, version, self.targets(info))?;
 
-        let checksums = get_checksums(
+        let (required, optional) = get_dependencies(
             info,
-            version,
-            vec![
-                Target::Aarch64AppleDarwin,
-                Target::X86_64AppleDarwin,
-                Target::X86_64UnknownLinuxGnu,
-            ],
-        )?;
+            info.homebrew
+                .as_ref()
+                .and_then(|homebrew| homebrew.dependencies.as_ref()),
+        );
 
-        write_and_add(&sh, &dir, format!("Formula/{name}.rb"), || {
+        let build = get_build(info);
+
+        let mut dependencies = (build == Build::Source)
+            .then(|| format!("  depends_on \"rust\" => :build"))
+            .into_iter()
+            .chain(required.iter().map(|d| format!("  depends_on {d:?}")))
+            .collect::<Vec<_>>();
+
+        if !dependencies.is_empty() {
+            dependencies.insert(0, format!(""));
+        }
+
+        let homebrew = info.homebrew.clone().unwrap_or_default();
+        let targets = self.targets(info);
+
+        let platform = |os: &str, arm: Target, intel: Target| {
+            let mut lines = vec![];
+
+            for (cpu, target) in [("arm", arm), ("intel", intel)] {
+                if targets.contains(&target) {
+                    lines.extend([
+                        format!("    on_{cpu} do"),
+                        format!(
+                            "      url \"https://github.com/{repository}/releases/download/v#{{version}}/{cli_name}-v#{{version}}-{target}.zip\""
+                        ),
+                        format!("      sha256 {:?}", checksums.get(&target).unwrap()),
+                        format!("    end"),
+                    ]);
+                }
+            }
+
+            if lines.is_empty() {
+                return lines;
+            }
+
+            [
+                vec![format!(""), format!("  on_{os} do")],
+                lines,
+                vec![format!("  end")],
+            ]
+            .concat()
+        };
+
+        let livecheck = homebrew.livecheck.clone().unwrap_or_default();
+        let livecheck = [
             vec![
-                format!("class {} < Formula", name.to_upper_camel_case()),
-                format!("  version {version:?}"),
-                format!("  desc {description:?}"),
-                format!("  homepage {homepage:?}"),
-                format!("  license {license:?}"),
                 format!(""),
-                format!("  if OS.mac?"),
-                format!("    if Hardware::CPU.arm?"),
-                format!(
-                    "      url \"https://github.com/{repository}/releases/download/v#{{version}}/{cli_name}-v#{{version}}-aarch64-apple-darwin.zip\""
-                ),
+                format!("  livecheck do"),
+                match &livecheck.url {
+                    Some(url) => format!("    url {url:?}"),
+                    None => format!("    url :stable"),
+                },
                 format!(
-                    "      sha256 {:?}",
-                    checksums.get(&Target::Aarch64AppleDarwin).unwrap()
+                    "    strategy :{}",
+                    livecheck.strategy.as_deref().unwrap_or("github_latest")
                 ),
-                format!("    else"),
-                format!(
-                    "      url \"https://github.com/{repository}/releases/download/v#{{version}}/{cli_name}-v#{{version}}-x86_64-apple-darwin.zip\""
-                ),
-                format!(
-                    "      sha256 {:?}",
-                    checksums.get(&Target::X86_64AppleDarwin).unwrap()
-                ),
-                format!("    end"),
-                format!("  elsif OS.linux?"),
-                format!(
-                    "     url \"https://github.com/{repository}/releases/download/v#{{version}}/{cli_name}-v#{{version}}-x86_64-unknown-linux-gnu.zip\""
-                ),
-                format!(
-                    "     sha256 {:?}",
-                    checksums.get(&Target::X86_64UnknownLinuxGnu).unwrap()
-                ),
-                format!("  end"),
-                format!(""),
-                format!("  def install"),
-                format!("    bin.install {cli_name:?}"),
-                format!("  end"),
-                format!(""),
-                format!("  test do"),
-                format!("    system \"#{{bin}}/{cli_name} --version\""),
-                format!("  end"),
-                format!("end"),
+            ],
+            livecheck
+                .regex
+                .iter()
+                .map(|regex| format!("    regex %r{{{regex}}}"))
+                .collect(),
+            vec![format!("  end")],
+        ]
+        .concat();
+
+        let mut conflicts = homebrew
+            .conflicts_with
+            .iter()
+            .flatten()
+            .map(|conflict| {
+                format!("  conflicts_with {conflict:?}, because: \"both install `{cli_name}`\"")
+            })
+            .collect::<Vec<_>>();
+
+        if !conflicts.is_empty() {
+            conflicts.insert(0, format!(""));
+        }
+
+        // Optional dependencies create deprecated `--with-*` options, so they are only mentioned
+        let mut caveats = homebrew
+            .caveats
+            .iter()
+            .flat_map(|caveats| caveats.lines())
+            .map(String::from)
+            .collect::<Vec<_>>();
+
+        if !optional.is_empty() {
+            if !caveats.is_empty() {
+                caveats.push(format!(""));
+            }
+
+            caveats.push(format!(
+                "Install {} for additional functionality.",
+                optional.join(", ")
+            ));
+        }
+
+        let caveats = if caveats.is_empty() {
+            vec![]
+        } else {
+            [
+                vec![format!(""), format!("  def caveats"), format!("    <<~EOS")],
+                caveats
+                    .iter()
+                    .map(|line| format!("      {line}").trim_end().to_string())
+                    .collect(),
+                vec![format!("    EOS"), format!("  end")],
             ]
+            .concat()
+        };
+
+        let service = get_service(info, homebrew.service.as_ref())
+            .map(|service| {
+                let run = service
+                    .args
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|arg| format!(", {arg:?}"))
+                    .collect::<String>();
+
+                [
+                    vec![
+                        format!(""),
+                        format!("  service do"),
+                        format!("    run [opt_bin/{cli_name:?}{run}]"),
+                    ],
+                    service
+                        .keep_alive
+                        .map(|keep_alive| format!("    keep_alive {keep_alive}"))
+                        .into_iter()
+                        .chain(
+                            service
+                                .working_dir
+                                .map(|d| format!("    working_dir {d:?}")),
+                        )
+                        .chain(service.log_path.map(|p| format!("    log_path {p:?}")))
+                        .chain(
+                            service
+                                .error_log_path
+                                .map(|p| format!("    error_log_path {p:?}")),
+                        )
+                        .collect(),
+                    vec![format!("  end")],
+                ]
+                .concat()
+            })
+            .unwrap_or_default();
+
+        let test = homebrew.test.clone().unwrap_or_default();
+        let command = test
+            .command
+            .unwrap_or_else(|| format!("{cli_name} --version"));
+
+        let test = match test.output {
+            Some(output) => {
+                format!("    assert_match %r{{{output}}}, shell_output(\"#{{bin}}/{command}\")")
+            }
+            None => format!("    system \"#{{bin}}/{command}\""),
+        };
+
+        let binaries = get_binaries(info)
+            .iter()
+            .map(|b| format!("{b:?}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let (sources, install) = match build {
+            Build::Binary => (
+                [
+                    platform(
+                        "macos",
+                        Target::Aarch64AppleDarwin,
+                        Target::X86_64AppleDarwin,
+                    ),
+                    platform(
+                        "linux",
+                        Target::Aarch64UnknownLinuxGnu,
+                        Target::X86_64UnknownLinuxGnu,
+                    ),
+                ]
+                .concat(),
+                format!("    bin.install {binaries}"),
+            ),
+            Build::Source => (
+                vec![
+                    format!(""),
+                    format!(
+                        "  url \"https://github.com/{repository}/archive/refs/tags/v#{{version}}.zip\""
+                    ),
+                    format!("  sha256 {:?}", checksums.get(&Target::Source).unwrap()),
+                ],
+                format!("    system \"cargo\", \"install\", *std_cargo_args"),
+            ),
+        };
+
+        write_and_add(&sh, &dir, format!("Formula/{name}.rb"), || {
+            [
+                vec![
+                    format!("class {} < Formula", name.to_upper_camel_case()),
+                    format!("  version {version:?}"),
+                    format!("  desc {description:?}"),
+                    format!("  homepage {homepage:?}"),
+                    format!("  license {license:?}"),
+                ],
+                livecheck,
+                dependencies,
+                conflicts,
+                sources,
+                vec![format!(""), format!("  def install"), install],
+                install_shell_files(info),
+                vec![format!("  end")],
+                caveats,
+                service,
+                vec![
+                    format!(""),
+                    format!("  test do"),
+                    test,
+                    format!("  end"),
+                    format!("end"),
+                ],
+            ]
+            .concat()
         })?;
 
-        if !dry_run {
+        if dry_run {
+            print_diff(&sh)?;
+        } else {
             commit_and_push(&sh, &name, version)?;
         }
 
@@ -153,7 +383,14 @@ impl Repository for Homebrew {
     }
 }
 
-fn get_name(info: &AppConfig) -> String {
+fn get_build(info: &AppConfig) -> Build {
+    info.homebrew
+        .as_ref()
+        .and_then(|homebrew| homebrew.build)
+        .unwrap_or_default()
+}
+
+pub(super) fn get_name(info: &AppConfig) -> String {
     info.homebrew
         .as_ref()
         .and_then(|homebrew| homebrew.name.clone())
@@ -166,3 +403,52 @@ fn get_repository(info: &AppConfig) -> String {
         .and_then(|homebrew| homebrew.repository.clone())
         .unwrap_or_else(|| info.repository.clone())
 }
+
+fn install_shell_files(info: &AppConfig) -> Vec<String> {
+    let cli_name = &info.name;
+
+    let mut lines = vec![];
+    let mut generated = vec![];
+    let mut command = None;
+
+    for (shell, completion) in get_completions(info) {
+        match completion {
+            Completion::File(path) => {
+                let file_name = match shell {
+                    "bash" => cli_name.clone(),
+                    "zsh" => format!("_{cli_name}"),
+                    _ => format!("{cli_name}.fish"),
+                };
+
+                lines.push(format!(
+                    "    {shell}_completion.install {path:?} => {file_name:?}"
+                ));
+            }
+            Completion::Command(c) => {
+                generated.push(format!(":{shell}"));
+                command = Some(c);
+            }
+        }
+    }
+
+    if let Some(command) = command {
+        let args = command
+            .split_whitespace()
+            .map(|arg| format!("{arg:?}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        lines.push(format!(
+            "    generate_completions_from_executable(bin/{cli_name:?}, {args}, shells: [{}])",
+            generated.join(", ")
+        ));
+    }
+
+    lines.extend(
+        get_manpages(info)
+            .into_iter()
+            .map(|(path, section, _)| format!("    man{section}.install {path:?}")),
+    );
+
+    lines
+}
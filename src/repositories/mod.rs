@@ -1,26 +1,46 @@
 use std::{
     collections::HashMap,
     fmt::{Debug, Display, Formatter, Result as FmtResult},
+    path::Path,
 };
 
 pub mod aur;
 pub mod aur_bin;
+pub mod aur_git;
 pub mod homebrew;
 pub mod nix;
 pub mod npm;
+mod pkgbuild;
 pub mod scoop;
 
 use clap::ValueEnum;
+use owo_colors::OwoColorize;
 use reqwest::blocking::get;
+use tracing::info;
 
-use crate::{check::CheckResults, config::AppConfig, error::Result, targets::Target};
+use crate::{
+    check::CheckResults,
+    config::{AppConfig, DependenciesConfig, Prerelease, ServiceConfig},
+    error::Result,
+    publish::PublishOptions,
+    targets::Target,
+};
 
 pub trait Repository {
     fn name(&self) -> &'static str;
 
     fn check(&self, check_result: &mut CheckResults, info: &AppConfig) -> Result;
 
-    fn publish(&self, info: &AppConfig, version: &str, dry_run: bool) -> Result;
+    fn targets(&self, info: &AppConfig) -> Vec<Target>;
+
+    fn publish(
+        &self,
+        info: &AppConfig,
+        version: &str,
+        dry_run: bool,
+        output_dir: &Path,
+        options: &PublishOptions,
+    ) -> Result;
 
     fn instructions(&self, info: &AppConfig) -> Result<Vec<String>>;
 }
@@ -31,6 +51,7 @@ pub enum Repositories {
     Homebrew,
     Aur,
     AurBin,
+    AurGit,
     Scoop,
     Nix,
     #[allow(clippy::upper_case_acronyms)]
@@ -49,6 +70,7 @@ impl Repositories {
             Repositories::Homebrew => Box::new(homebrew::Homebrew),
             Repositories::Aur => Box::new(aur::Aur),
             Repositories::AurBin => Box::new(aur_bin::AurBin),
+            Repositories::AurGit => Box::new(aur_git::AurGit),
             Repositories::Scoop => Box::new(scoop::Scoop),
             Repositories::Nix => Box::new(nix::Nix),
             Repositories::NPM => Box::new(npm::NPM),
@@ -59,14 +81,20 @@ impl Repositories {
 fn get_repositories<'a>(
     repositories: &'a [Repositories],
     exclude: &'a [String],
+    config: &AppConfig,
 ) -> Vec<&'a Repositories> {
     let repos = if !repositories.is_empty() {
-        repositories.iter()
+        repositories.iter().collect::<Vec<_>>()
     } else {
-        Repositories::value_variants().iter()
+        // AUR (git) creates a new package, so it is only selected by default once configured
+        Repositories::value_variants()
+            .iter()
+            .filter(|r| **r != Repositories::AurGit || config.aur_git.is_some())
+            .collect()
     };
 
     repos
+        .into_iter()
         .filter(|r| {
             let v = r.to_possible_value().unwrap();
             !exclude.iter().any(|e| v.matches(e, true))
@@ -74,35 +102,125 @@ fn get_repositories<'a>(
         .collect()
 }
 
-pub fn build(repositories: &[Repositories], exclude: &[String]) -> Vec<Box<dyn Repository>> {
-    get_repositories(repositories, exclude)
+pub fn build(
+    repositories: &[Repositories],
+    exclude: &[String],
+    config: &AppConfig,
+) -> Vec<Box<dyn Repository>> {
+    get_repositories(repositories, exclude, config)
         .into_iter()
         .map(Repositories::build)
         .collect()
 }
 
 pub fn update_config(repositories: &[Repositories], exclude: &[String], config: &mut AppConfig) {
-    let repos = get_repositories(repositories, exclude);
+    let repos = get_repositories(repositories, exclude, config);
 
-    // Add conflicts between AUR and AUR (bin) if both are selected
-    if repos.iter().any(|r| r == &&Repositories::Aur)
-        && repos.iter().any(|r| r == &&Repositories::AurBin)
-    {
-        let aur_name = aur::get_name(config);
-        let aur_bin_name = aur_bin::get_name(config);
+    // Add conflicts between the AUR packages that are selected
+    let aur_names = repos
+        .iter()
+        .filter_map(|r| match r {
+            Repositories::Aur => Some((*r, aur::get_name(config))),
+            Repositories::AurBin => Some((*r, aur_bin::get_name(config))),
+            Repositories::AurGit => Some((*r, aur_git::get_name(config))),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
 
-        config
-            .aur
-            .get_or_insert_with(Default::default)
-            .conflicts
-            .get_or_insert_with(Default::default)
-            .push(aur_bin_name);
-        config
-            .aur_bin
-            .get_or_insert_with(Default::default)
-            .conflicts
+    for (repo, _) in &aur_names {
+        let others = aur_names
+            .iter()
+            .filter(|(r, _)| r != repo)
+            .map(|(_, name)| name.clone());
+
+        let conflicts = match repo {
+            Repositories::Aur => &mut config.aur.get_or_insert_with(Default::default).conflicts,
+            Repositories::AurBin => {
+                &mut config
+                    .aur_bin
+                    .get_or_insert_with(Default::default)
+                    .conflicts
+            }
+            Repositories::AurGit => {
+                &mut config
+                    .aur_git
+                    .get_or_insert_with(Default::default)
+                    .conflicts
+            }
+            _ => unreachable!(),
+        };
+
+        conflicts
             .get_or_insert_with(Default::default)
-            .push(aur_name);
+            .extend(others);
+    }
+}
+
+// Routes pre-releases away from the stable packages of the selected repositories
+pub fn update_prerelease(
+    repositories: &[Repositories],
+    exclude: &mut Vec<String>,
+    config: &mut AppConfig,
+) {
+    let mode = config.prerelease.unwrap_or_default();
+    let beta = |name: String| format!("{name}-beta");
+
+    let selected = get_repositories(repositories, exclude, config)
+        .into_iter()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    for repo in selected {
+        match (repo, mode) {
+            // NPM uses a dist-tag and AUR (git) is always built from the default branch
+            (Repositories::NPM | Repositories::AurGit, _) => {}
+            // Nix always writes pre-releases to a separate flake
+            (Repositories::Nix, _) => {
+                let name = beta(nix::get_name(config));
+                let path = nix::get_prerelease_path(config);
+                let nix = config.nix.get_or_insert_with(Default::default);
+
+                nix.name = Some(name);
+                nix.path = Some(path);
+            }
+            (repo, Prerelease::Skip) => {
+                info!("{} {repo} for pre-release", "skipping".yellow());
+                exclude.push(repo.to_possible_value().unwrap().get_name().into());
+            }
+            (Repositories::Homebrew, Prerelease::Separate) => {
+                let stable = homebrew::get_name(config);
+                let homebrew = config.homebrew.get_or_insert_with(Default::default);
+
+                homebrew.name = Some(beta(stable.clone()));
+                homebrew
+                    .conflicts_with
+                    .get_or_insert_with(Default::default)
+                    .push(stable);
+            }
+            (Repositories::Scoop, Prerelease::Separate) => {
+                let name = beta(scoop::get_name(config));
+                config.scoop.get_or_insert_with(Default::default).name = Some(name);
+            }
+            (Repositories::Aur, Prerelease::Separate) => {
+                let stable = aur::get_name(config);
+                let aur = config.aur.get_or_insert_with(Default::default);
+
+                aur.name = Some(beta(stable.clone()));
+                aur.conflicts
+                    .get_or_insert_with(Default::default)
+                    .push(stable);
+            }
+            (Repositories::AurBin, Prerelease::Separate) => {
+                let stable = aur_bin::get_name(config);
+                let aur_bin = config.aur_bin.get_or_insert_with(Default::default);
+
+                aur_bin.name = Some(beta(stable.clone()));
+                aur_bin
+                    .conflicts
+                    .get_or_insert_with(Default::default)
+                    .push(stable);
+            }
+        }
     }
 }
 
@@ -136,3 +254,146 @@ fn get_checksums(
         })
         .collect()
 }
+
+pub fn get_binaries(info: &AppConfig) -> Vec<String> {
+    info.binaries
+        .clone()
+        .unwrap_or_else(|| vec![info.name.clone()])
+}
+
+pub fn get_targets(info: &AppConfig) -> Vec<Target> {
+    info.targets.clone().unwrap_or_else(Target::defaults)
+}
+
+// Returns the required and optional dependencies, preferring the repository specific ones
+fn get_dependencies(
+    info: &AppConfig,
+    dependencies: Option<&DependenciesConfig>,
+) -> (Vec<String>, Vec<String>) {
+    let generic = info.dependencies.as_ref();
+
+    let required = dependencies
+        .and_then(|d| d.required.clone())
+        .or_else(|| generic.and_then(|d| d.required.clone()))
+        .unwrap_or_default();
+
+    let optional = dependencies
+        .and_then(|d| d.optional.clone())
+        .or_else(|| generic.and_then(|d| d.optional.clone()))
+        .unwrap_or_default();
+
+    (required, optional)
+}
+
+// Returns the service, preferring the repository specific one
+fn get_service(info: &AppConfig, service: Option<&ServiceConfig>) -> Option<ServiceConfig> {
+    service.or(info.service.as_ref()).cloned()
+}
+
+// Returns the systemd user unit running the service from the given directory of binaries
+fn get_systemd_unit(info: &AppConfig, bin_dir: &str) -> Vec<String> {
+    let Some(service) = get_service(info, None) else {
+        return vec![];
+    };
+
+    let args = service
+        .args
+        .unwrap_or_default()
+        .iter()
+        .map(|arg| {
+            if arg.contains(char::is_whitespace) {
+                format!("{arg:?}")
+            } else {
+                arg.clone()
+            }
+        })
+        .fold(format!("{bin_dir}/{}", info.name), |command, arg| {
+            format!("{command} {arg}")
+        });
+
+    [
+        vec![
+            format!("[Unit]"),
+            format!("Description={}", info.description),
+            format!(""),
+            format!("[Service]"),
+            format!("ExecStart={args}"),
+        ],
+        service
+            .keep_alive
+            .unwrap_or_default()
+            .then(|| format!("Restart=always"))
+            .into_iter()
+            .chain(service.working_dir.map(|d| format!("WorkingDirectory={d}")))
+            .chain(
+                service
+                    .log_path
+                    .map(|p| format!("StandardOutput=append:{p}")),
+            )
+            .chain(
+                service
+                    .error_log_path
+                    .map(|p| format!("StandardError=append:{p}")),
+            )
+            .collect(),
+        vec![
+            format!(""),
+            format!("[Install]"),
+            format!("WantedBy=default.target"),
+        ],
+    ]
+    .concat()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Completion {
+    // Path of the completion script in the artifact or the source
+    File(String),
+    // Subcommand of the binary which prints the completion script for the shell
+    Command(String),
+}
+
+// Returns the completions for each shell, preferring the files over the command
+pub fn get_completions(info: &AppConfig) -> Vec<(&'static str, Completion)> {
+    let Some(completions) = info.completions.as_ref() else {
+        return vec![];
+    };
+
+    [
+        ("bash", &completions.bash),
+        ("zsh", &completions.zsh),
+        ("fish", &completions.fish),
+    ]
+    .into_iter()
+    .filter_map(|(shell, file)| {
+        file.clone()
+            .map(Completion::File)
+            .or_else(|| completions.command.clone().map(Completion::Command))
+            .map(|completion| (shell, completion))
+    })
+    .collect()
+}
+
+// Returns the path, section and file name of each man page
+pub fn get_manpages(info: &AppConfig) -> Vec<(String, String, String)> {
+    info.manpages
+        .clone()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|path| {
+            let path_ref = Path::new(&path);
+
+            let section = path_ref
+                .extension()
+                .map(|e| e.to_string_lossy().to_string())
+                .unwrap_or_else(|| "1".into());
+
+            let file_name = path_ref
+                .file_name()
+                .map(|f| f.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            (path, section, file_name)
+        })
+        .collect()
+}
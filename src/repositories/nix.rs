@@ -1,12 +1,22 @@
+use std::{
+    fs::{read_dir, read_to_string},
+    path::Path,
+};
+
+use eyre::eyre;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, to_string_pretty};
 use xshell::{Shell, cmd};
 
-use super::get_checksums;
+use super::{
+    Completion, get_binaries, get_checksums, get_completions, get_dependencies, get_manpages,
+    get_systemd_unit, get_targets,
+};
 use crate::{
     check::{CheckResults, check_git, check_program, check_repo},
-    config::AppConfig,
+    config::{AppConfig, Build, DependenciesConfig},
     error::Result,
-    publish::{commit_and_push, prepare_git_repo, write_and_add},
+    publish::{PublishOptions, commit_and_push, prepare_git_repo, print_diff, write_and_add},
     repositories::Repository,
     targets::Target,
 };
@@ -17,6 +27,21 @@ pub struct NixConfig {
     pub repository: Option<String>,
     pub path: Option<String>,
     pub lockfile: Option<bool>,
+    pub nixpkgs: Option<String>,
+    pub nixpkgs_rev: Option<String>,
+    pub nixpkgs_nar_hash: Option<String>,
+    pub prerelease_path: Option<String>,
+    pub dependencies: Option<DependenciesConfig>,
+    pub build: Option<Build>,
+    pub cargo_hash: Option<String>,
+    pub apps: Option<bool>,
+    pub overlay: Option<bool>,
+    pub module: Option<NixModuleConfig>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct NixModuleConfig {
+    pub namespace: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -42,16 +67,55 @@ impl Repository for Nix {
             false,
         )?;
 
+        if get_build(info) == Build::Source && get_cargo_hash(info).is_none() {
+            let tracked = cmd!(sh, "git ls-files --error-unmatch :/Cargo.lock")
+                .quiet()
+                .ignore_stdout()
+                .ignore_stderr()
+                .run()
+                .is_ok();
+
+            results.add_result(
+                "Cargo.lock",
+                (!tracked).then_some("Cargo.lock file is not committed"),
+            );
+        }
+
         let lockfile = get_lockfile(info);
 
-        if lockfile {
+        let pin = get_nixpkgs_pin(info);
+
+        if let Err(e) = &pin {
+            results.add_result("nixpkgs pin", Some(e.to_string()));
+        }
+
+        // The lock file is written by us when nixpkgs is pinned
+        if lockfile && matches!(pin, Ok(None)) {
             check_program(&sh, results, "nix", "nix --version", "nix (Nix) ");
         }
 
         Ok(())
     }
 
-    fn publish(&self, info: &AppConfig, version: &str, dry_run: bool) -> Result {
+    fn targets(&self, info: &AppConfig) -> Vec<Target> {
+        if get_build(info) == Build::Source {
+            return vec![Target::Source];
+        }
+
+        get_systems(info)
+            .into_iter()
+            .map(|(target, _)| target)
+            .collect()
+    }
+
+    fn publish(
+        &self,
+        info: &AppConfig,
+        version: &str,
+        dry_run: bool,
+        output_dir: &Path,
+        _: &PublishOptions,
+    ) -> Result {
         let AppConfig {
             name: cli_name,
             description,
@@ -64,111 +128,462 @@ impl Repository for Nix {
         let pkg_repo = get_repository(info);
         let path = get_path(info, &name);
         let lockfile = get_lockfile(info);
-        let (sh, dir) = prepare_git_repo(self, &format!("git@github.com:{pkg_repo}"))?;
+        let (sh, dir) = prepare_git_repo(self, output_dir, &format!("git@github.com:{pkg_repo}"))?;
 
-        let checksums = get_checksums(
+        let checksums = get_checksums(info, version, self.targets(info))?;
+
+        // Nix has no optional dependencies, so we only use the required ones
+        let (required, _) = get_dependencies(
             info,
-            version,
-            vec![
-                Target::Aarch64AppleDarwin,
-                Target::X86_64AppleDarwin,
-                Target::X86_64UnknownLinuxGnu,
-                Target::I686UnknownLinuxGnu,
-            ],
-        )?;
+            info.nix.as_ref().and_then(|nix| nix.dependencies.as_ref()),
+        );
 
-        write_and_add(&sh, &dir, path, || {
-            vec![
-                format!("{{"),
-                format!("  description = {description:?};"),
-                format!(""),
-                format!("  inputs = {{"),
-                format!("    nixpkgs.url = \"github:NixOS/nixpkgs\";"),
-                format!("    flake-utils.url = \"github:numtide/flake-utils\";"),
-                format!("  }};"),
-                format!(""),
-                format!("  outputs = {{ self, nixpkgs, flake-utils }}:"),
-                format!("    with flake-utils.lib;"),
-                format!("    with nixpkgs.lib;"),
-                format!(""),
-                format!("    let"),
-                format!("      systems = {{"),
-                format!("        aarch64-darwin = {{"),
-                format!("          target = \"aarch64-apple-darwin\";"),
-                format!(
-                    "          sha256 = {:?};",
-                    checksums.get(&Target::Aarch64AppleDarwin).unwrap()
-                ),
-                format!("        }};"),
-                format!("        x86_64-darwin = {{"),
-                format!("          target = \"x86_64-apple-darwin\";"),
-                format!(
-                    "          sha256 = {:?};",
-                    checksums.get(&Target::X86_64AppleDarwin).unwrap()
-                ),
-                format!("        }};"),
-                format!("        x86_64-linux = {{"),
-                format!("          target = \"x86_64-unknown-linux-gnu\";"),
-                format!(
-                    "          sha256 = {:?};",
-                    checksums.get(&Target::X86_64UnknownLinuxGnu).unwrap()
-                ),
-                format!("        }};"),
-                format!("        i686-linux = {{"),
-                format!("          target = \"i686-unknown-linux-gnu\";"),
-                format!(
-                    "          sha256 = {:?};",
-                    checksums.get(&Target::I686UnknownLinuxGnu).unwrap()
-                ),
-                format!("        }};"),
-                format!("      }};"),
-                format!("    in eachSystem (mapAttrsToList (n: v: n) systems) (system: {{"),
-                format!("      packages.default = with import nixpkgs {{ inherit system; }};"),
-                format!(""),
-                format!("        stdenv.mkDerivation rec {{"),
-                format!("          name = \"{name}-${{version}}\";"),
-                format!("          version = {version:?};"),
-                format!(""),
-                format!("          nativeBuildInputs = [ unzip ];"),
-                format!(""),
-                format!("          src = pkgs.fetchurl {{"),
+        let build = get_build(info);
+
+        let mut native_build_inputs = vec!["unzip"];
+
+        // `buildRustPackage` installs the binaries by itself
+        let mut install = match build {
+            Build::Binary => get_binaries(info)
+                .into_iter()
+                .map(|binary| format!("    install -Dm755 {binary} $out/bin/{binary}"))
+                .collect::<Vec<_>>(),
+            Build::Source => vec![],
+        };
+
+        install.push(format!(
+            "    install -Dm755 LICENSE $out/share/licenses/{cli_name}/LICENSE"
+        ));
+
+        let completions = get_completions(info)
+            .into_iter()
+            .map(|(shell, completion)| match completion {
+                Completion::File(path) => format!("--{shell} {path}"),
+                Completion::Command(command) => {
+                    format!("--{shell} <($out/bin/{cli_name} {command} {shell})")
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let manpages = get_manpages(info)
+            .into_iter()
+            .map(|(path, _, _)| path)
+            .collect::<Vec<_>>();
+
+        if !completions.is_empty() || !manpages.is_empty() {
+            native_build_inputs.push("installShellFiles");
+        }
+
+        // The downloaded binaries need to be patched before they can run in the build sandbox
+        if build == Build::Binary
+            && get_completions(info)
+                .iter()
+                .any(|(_, completion)| matches!(completion, Completion::Command(_)))
+        {
+            install.push(format!(
+                "    ${{lib.optionalString stdenv.hostPlatform.isLinux \"autoPatchelf $out/bin\"}}"
+            ));
+        }
+
+        if !completions.is_empty() {
+            install.push(format!(
+                "    installShellCompletion --cmd {cli_name} {}",
+                completions.join(" ")
+            ));
+        }
+
+        if !manpages.is_empty() {
+            install.push(format!("    installManPage {}", manpages.join(" ")));
+        }
+
+        let unit = get_systemd_unit(info, "$out/bin");
+
+        // Indented like the rest so that the heredoc ends up unindented in the build script
+        if !unit.is_empty() {
+            install.extend([
+                format!("    mkdir -p $out/lib/systemd/user"),
+                format!("    cat > $out/lib/systemd/user/{cli_name}.service <<EOF"),
+            ]);
+            install.extend(
+                unit.iter()
+                    .map(|line| format!("    {line}").trim_end().to_string()),
+            );
+            install.push(format!("    EOF"));
+        }
+
+        if !required.is_empty() {
+            let inputs = required.join(" ");
+
+            native_build_inputs.push("makeWrapper");
+            install.extend(get_binaries(info).into_iter().map(|binary| {
                 format!(
-                    "            url = \"https://github.com/{repository}/releases/download/v${{version}}/{cli_name}-v${{version}}-${{systems.${{system}}.target}}.zip\";"
-                ),
-                format!("            inherit (systems.${{system}}) sha256;"),
-                format!("          }};"),
-                format!(""),
-                format!("          sourceRoot = \".\";"),
+                    "    wrapProgram $out/bin/{binary} --prefix PATH : ${{lib.makeBinPath [ {inputs} ]}}"
+                )
+            }));
+        }
+
+        // Pre-built glibc binaries are linked against the usual loader, which does not exist on NixOS
+        let native_build_inputs = native_build_inputs.join(" ");
+        let build_inputs = required.join(" ");
+
+        let (native_build_inputs, build_inputs) = match build {
+            Build::Binary => {
+                let build_inputs = if required.is_empty() {
+                    format!("lib.optional stdenv.hostPlatform.isLinux stdenv.cc.cc.lib")
+                } else {
+                    format!(
+                        "[ {build_inputs} ] ++ lib.optional stdenv.hostPlatform.isLinux stdenv.cc.cc.lib"
+                    )
+                };
+
+                (
+                    format!(
+                        "[ {native_build_inputs} ] ++ lib.optional stdenv.hostPlatform.isLinux autoPatchelfHook"
+                    ),
+                    vec![format!("  buildInputs = {build_inputs};")],
+                )
+            }
+            Build::Source if required.is_empty() => (format!("[ {native_build_inputs} ]"), vec![]),
+            Build::Source => (
+                format!("[ {native_build_inputs} ]"),
+                vec![format!("  buildInputs = [ {build_inputs} ];")],
+            ),
+        };
+
+        // A package file, instead of a flake, is collected into the top-level flake
+        let standalone = Path::new(&path).file_name() == Some("flake.nix".as_ref());
+
+        // Package files can share a directory, so their lock files are prefixed with the name
+        let lock_file = if standalone {
+            format!("Cargo.lock")
+        } else {
+            format!("{name}-Cargo.lock")
+        };
+
+        let systems = get_systems(info);
+
+        if build == Build::Binary && systems.is_empty() {
+            return Err(eyre!(
+                "No darwin or linux gnu targets are configured for Nix"
+            ));
+        }
+
+        let (systems, header, src, phase) = match build {
+            Build::Binary => (
+                [
+                    vec![format!("systems = {{")],
+                    systems
+                        .iter()
+                        .flat_map(|(target, system)| {
+                            [
+                                format!("  {system} = {{"),
+                                format!("    target = \"{target}\";"),
+                                format!("    sha256 = {:?};", checksums.get(target).unwrap()),
+                                format!("  }};"),
+                            ]
+                        })
+                        .collect(),
+                    vec![format!("}};")],
+                ]
+                .concat(),
+                vec![
+                    format!("stdenv.mkDerivation rec {{"),
+                    format!("  name = \"{name}-${{version}}\";"),
+                ],
+                vec![
+                    format!("  src = pkgs.fetchurl {{"),
+                    format!(
+                        "    url = \"https://github.com/{repository}/releases/download/v${{version}}/{cli_name}-v${{version}}-${{systems.${{system}}.target}}.zip\";"
+                    ),
+                    format!("    inherit (systems.${{system}}) sha256;"),
+                    format!("  }};"),
+                    format!(""),
+                    format!("  sourceRoot = \".\";"),
+                ],
+                format!("installPhase"),
+            ),
+            Build::Source => (
+                vec![],
+                vec![
+                    format!("rustPlatform.buildRustPackage rec {{"),
+                    format!("  pname = {name:?};"),
+                ],
+                vec![
+                    format!("  src = pkgs.fetchurl {{"),
+                    format!(
+                        "    url = \"https://github.com/{repository}/archive/refs/tags/v${{version}}.zip\";"
+                    ),
+                    format!(
+                        "    sha256 = {:?};",
+                        checksums.get(&Target::Source).unwrap()
+                    ),
+                    format!("  }};"),
+                    format!(""),
+                    match get_cargo_hash(info) {
+                        Some(hash) => format!("  cargoHash = {hash:?};"),
+                        None => format!("  cargoLock.lockFile = ./{lock_file};"),
+                    },
+                ],
+                format!("postInstall"),
+            ),
+        };
+
+        let platforms = match (build, standalone) {
+            (Build::Binary, true) => vec![format!("    platforms = [ system ];")],
+            (Build::Binary, false) => vec![format!("    platforms = lib.attrNames systems;")],
+            (Build::Source, _) => vec![],
+        };
+
+        let derivation = [
+            header,
+            vec![
+                format!("  version = {version:?};"),
                 format!(""),
-                format!("          installPhase = ''"),
-                format!("            install -Dm755 {cli_name} $out/bin/{cli_name}"),
-                format!(
-                    "            install -Dm755 LICENSE $out/share/licenses/{cli_name}/LICENSE"
-                ),
-                format!("          '';"),
+                format!("  nativeBuildInputs = {native_build_inputs};"),
+            ],
+            build_inputs,
+            vec![format!("")],
+            src,
+            vec![format!(""), format!("  {phase} = ''")],
+            install,
+            vec![
+                format!("  '';"),
                 format!(""),
-                format!("          meta = {{"),
-                format!("            description = {description:?};"),
-                format!("            homepage = {homepage:?};"),
-                format!("            platforms = [ system ];"),
-                format!("          }};"),
-                format!("        }};"),
-                format!("    }});"),
-                format!("}}"),
+                format!("  meta = {{"),
+                format!("    description = {description:?};"),
+                format!("    homepage = {homepage:?};"),
+            ],
+            platforms,
+            vec![format!("  }};")],
+        ]
+        .concat();
+
+        write_and_add(&sh, &dir, &path, || {
+            if !standalone {
+                let systems = if systems.is_empty() {
+                    vec![]
+                } else {
+                    [
+                        vec![
+                            format!("let"),
+                            format!("  system = stdenv.hostPlatform.system;"),
+                        ],
+                        indent(&systems, 2),
+                        vec![format!("in")],
+                    ]
+                    .concat()
+                };
+
+                return [
+                    vec![
+                        format!("{{ pkgs }}:"),
+                        format!(""),
+                        format!("with pkgs;"),
+                        format!(""),
+                    ],
+                    systems,
+                    derivation,
+                    vec![format!("}}")],
+                ]
+                .concat();
+            }
+
+            let outputs = if systems.is_empty() {
+                vec![format!("    eachDefaultSystem (system: {{")]
+            } else {
+                [
+                    vec![format!("    let")],
+                    indent(&systems, 6),
+                    vec![format!(
+                        "    in eachSystem (mapAttrsToList (n: v: n) systems) (system: {{"
+                    )],
+                ]
+                .concat()
+            };
+
+            let apps = if get_apps(info) {
+                vec![
+                    format!(""),
+                    format!("      apps.default = {{"),
+                    format!("        type = \"app\";"),
+                    format!(
+                        "        program = \"${{self.packages.${{system}}.default}}/bin/{cli_name}\";"
+                    ),
+                    format!("      }};"),
+                ]
+            } else {
+                vec![]
+            };
+
+            let mut extra = vec![];
+
+            if get_overlay(info) {
+                extra.extend([
+                    format!("      overlays.default = final: prev: {{"),
+                    format!(
+                        "        {name} = self.packages.${{final.stdenv.hostPlatform.system}}.default;"
+                    ),
+                    format!("      }};"),
+                ]);
+            }
+
+            if let Some(module) = info.nix.as_ref().and_then(|nix| nix.module.as_ref()) {
+                let namespace = module
+                    .namespace
+                    .clone()
+                    .unwrap_or_else(|| format!("programs.{name}"));
+
+                for (output, packages) in [
+                    ("nixosModules", "environment.systemPackages"),
+                    ("homeManagerModules", "home.packages"),
+                ] {
+                    if !extra.is_empty() {
+                        extra.push(format!(""));
+                    }
+
+                    extra.extend([
+                        format!("      {output}.default = {{ config, lib, pkgs, ... }}:"),
+                        format!("        let"),
+                        format!("          cfg = config.{namespace};"),
+                        format!("        in {{"),
+                        format!("          options.{namespace} = {{"),
+                        format!("            enable = lib.mkEnableOption {cli_name:?};"),
+                        format!(""),
+                        format!("            package = lib.mkOption {{"),
+                        format!("              type = lib.types.package;"),
+                        format!(
+                            "              default = self.packages.${{pkgs.stdenv.hostPlatform.system}}.default;"
+                        ),
+                        format!("              description = \"The {cli_name} package to use.\";"),
+                        format!("            }};"),
+                        format!("          }};"),
+                        format!(""),
+                        format!("          config = lib.mkIf cfg.enable {{"),
+                        format!("            {packages} = [ cfg.package ];"),
+                        format!("          }};"),
+                        format!("        }};"),
+                    ]);
+                }
+            }
+
+            let end = if extra.is_empty() {
+                vec![format!("    }});")]
+            } else {
+                [
+                    vec![format!("    }}) // {{")],
+                    extra,
+                    vec![format!("    }};")],
+                ]
+                .concat()
+            };
+
+            [
+                flake_header(info, description),
+                outputs,
+                vec![
+                    format!("      packages.default = with import nixpkgs {{ inherit system; }};"),
+                    format!(""),
+                ],
+                indent(&derivation, 8),
+                vec![format!("        }};")],
+                apps,
+                end,
+                vec![format!("}}")],
             ]
+            .concat()
         })?;
 
-        if lockfile {
-            cmd!(
-                sh,
-                "nix --extra-experimental-features 'nix-command flakes' flake update"
-            )
-            .run()?;
+        // The lock file is needed at evaluation time, so it is kept next to the package. It is
+        // taken from the tag since it needs to match the one in the source archive
+        if build == Build::Source && get_cargo_hash(info).is_none() {
+            let source_sh = Shell::new()?;
+            let cargo_lock = cmd!(source_sh, "git show v{version}:Cargo.lock")
+                .quiet()
+                .read()
+                .map_err(|_| eyre!("Unable to read Cargo.lock of the tag v{version}"))?;
+            let lock_path = Path::new(&path).with_file_name(lock_file);
+
+            write_and_add(&sh, &dir, lock_path.to_string_lossy(), || {
+                cargo_lock.lines().map(String::from).collect()
+            })?;
+        }
+
+        if !standalone {
+            let pattern = info
+                .nix
+                .as_ref()
+                .and_then(|nix| nix.path.clone())
+                .unwrap_or_default();
+
+            // Without `%n`, the path can only hold this package
+            let packages = if pattern.contains("%n") {
+                get_packages(Path::new(&dir), Path::new(&dir), &pattern)?
+            } else {
+                vec![(name.clone(), path.clone())]
+            };
 
-            cmd!(sh, "git add flake.lock").quiet().run()?;
+            let packages = packages
+                .into_iter()
+                .map(|(name, path)| format!("        {name} = ./{path};"))
+                .collect::<Vec<_>>();
+
+            write_and_add(&sh, &dir, "flake.nix", || {
+                [
+                    flake_header(info, &format!("Packages from {pkg_repo}")),
+                    vec![format!("    let"), format!("      packages = {{")],
+                    packages,
+                    vec![
+                        format!("      }};"),
+                        format!(""),
+                        format!(
+                            "      build = pkgs: mapAttrs (name: path: import path {{ inherit pkgs; }}) packages;"
+                        ),
+                        format!("    in eachDefaultSystem (system: {{"),
+                        format!(
+                            "      packages = filterAttrs (name: meta.availableOn {{ inherit system; }}) (build nixpkgs.legacyPackages.${{system}});"
+                        ),
+                        format!("    }}) // {{"),
+                        format!("      overlays.default = final: prev: build final;"),
+                        format!("    }};"),
+                        format!("}}"),
+                    ],
+                ]
+                .concat()
+            })?;
         }
 
-        if !dry_run {
+        // The lock belongs to the flake that was written, which might not be at the root
+        let lock_path = if standalone {
+            Path::new(&path).with_file_name("flake.lock")
+        } else {
+            "flake.lock".into()
+        };
+
+        if let Some((rev, nar_hash)) = get_nixpkgs_pin(info)? {
+            let lock = get_flake_lock(info, &rev, &nar_hash)?;
+
+            write_and_add(&sh, &dir, lock_path.to_string_lossy(), || {
+                lock.lines().map(String::from).collect()
+            })?;
+        } else if lockfile {
+            let flake_dir = Path::new(&dir).join(lock_path.parent().unwrap());
+
+            {
+                let _dir = sh.push_dir(flake_dir);
+
+                cmd!(
+                    sh,
+                    "nix --extra-experimental-features 'nix-command flakes' flake update"
+                )
+                .run()?;
+            }
+
+            cmd!(sh, "git add {lock_path}").quiet().run()?;
+        }
+
+        if dry_run {
+            print_diff(&sh)?;
+        } else {
             commit_and_push(&sh, &name, version)?;
         }
 
@@ -196,7 +611,7 @@ impl Repository for Nix {
     }
 }
 
-fn get_name(info: &AppConfig) -> String {
+pub(super) fn get_name(info: &AppConfig) -> String {
     info.nix
         .as_ref()
         .and_then(|nix| nix.name.clone())
@@ -218,9 +633,279 @@ fn get_path(info: &AppConfig, name: &str) -> String {
         .unwrap_or_else(|| "flake.nix".into())
 }
 
+pub(super) fn get_prerelease_path(info: &AppConfig) -> String {
+    info.nix
+        .as_ref()
+        .and_then(|nix| nix.prerelease_path.clone())
+        .unwrap_or_else(|| "%n/flake.nix".into())
+}
+
+fn get_nixpkgs(info: &AppConfig) -> String {
+    info.nix
+        .as_ref()
+        .and_then(|nix| nix.nixpkgs.clone())
+        .unwrap_or_else(|| "github:NixOS/nixpkgs".into())
+}
+
+fn get_nixpkgs_pin(info: &AppConfig) -> Result<Option<(String, String)>> {
+    let Some(nix) = info.nix.as_ref() else {
+        return Ok(None);
+    };
+
+    match (&nix.nixpkgs_rev, &nix.nixpkgs_nar_hash) {
+        (Some(rev), Some(nar_hash)) => Ok(Some((rev.clone(), nar_hash.clone()))),
+        (None, None) => Ok(None),
+        _ => Err(eyre!(
+            "Both nixpkgs_rev and nixpkgs_nar_hash need to be configured to pin nixpkgs"
+        )),
+    }
+}
+
+// Builds the lock file from the pinned nixpkgs and fixed revisions of
+// flake-utils and systems, so that nix is not needed to publish
+fn get_flake_lock(info: &AppConfig, rev: &str, nar_hash: &str) -> Result<String> {
+    let nixpkgs = get_nixpkgs(info);
+
+    let mut original = match nixpkgs
+        .strip_prefix("github:")
+        .map(|path| path.split('/').collect::<Vec<_>>())
+        .as_deref()
+    {
+        Some([owner, repo]) => json!({ "owner": owner, "repo": repo }),
+        Some([owner, repo, reference]) => {
+            json!({ "owner": owner, "repo": repo, "ref": reference })
+        }
+        _ => {
+            return Err(eyre!(
+                "Unable to pin nixpkgs {nixpkgs:?}, only github:owner/repo[/ref] is supported"
+            ));
+        }
+    };
+
+    original["type"] = json!("github");
+
+    let mut locked = original.clone();
+
+    if let Some(locked) = locked.as_object_mut() {
+        locked.remove("ref");
+        locked.insert("rev".into(), json!(rev));
+        locked.insert("narHash".into(), json!(nar_hash));
+    }
+
+    let lock = json!({
+        "nodes": {
+            "flake-utils": {
+                "inputs": {
+                    "systems": "systems"
+                },
+                "locked": {
+                    "lastModified": 1731533236,
+                    "narHash": "sha256-l0KFg5HjrsfsO/JpG+r7fRrqm12kzFHyUHqHCVpMMbI=",
+                    "owner": "numtide",
+                    "repo": "flake-utils",
+                    "rev": "11707dc2f618dd54ca8739b309ec4fc024de578b",
+                    "type": "github"
+                },
+                "original": {
+                    "owner": "numtide",
+                    "repo": "flake-utils",
+                    "type": "github"
+                }
+            },
+            "nixpkgs": {
+                "locked": locked,
+                "original": original
+            },
+            "root": {
+                "inputs": {
+                    "flake-utils": "flake-utils",
+                    "nixpkgs": "nixpkgs"
+                }
+            },
+            "systems": {
+                "locked": {
+                    "lastModified": 1681028828,
+                    "narHash": "sha256-Vy1rq5AaRuLzOxct8nz4T6wlgyUR7zLU309k9mBC768=",
+                    "owner": "nix-systems",
+                    "repo": "default",
+                    "rev": "da67096a3b9bf56a91d16901293e51ba5b49a27e",
+                    "type": "github"
+                },
+                "original": {
+                    "owner": "nix-systems",
+                    "repo": "default",
+                    "type": "github"
+                }
+            }
+        },
+        "root": "root",
+        "version": 7
+    });
+
+    Ok(to_string_pretty(&lock)?)
+}
+
 fn get_lockfile(info: &AppConfig) -> bool {
     info.nix
         .as_ref()
         .and_then(|nix| nix.lockfile)
         .unwrap_or(true)
 }
+
+// Nix systems of the configured targets that have binaries
+fn get_systems(info: &AppConfig) -> Vec<(Target, &'static str)> {
+    let targets = get_targets(info);
+
+    [
+        (Target::Aarch64AppleDarwin, "aarch64-darwin"),
+        (Target::X86_64AppleDarwin, "x86_64-darwin"),
+        (Target::X86_64UnknownLinuxGnu, "x86_64-linux"),
+        (Target::I686UnknownLinuxGnu, "i686-linux"),
+        (Target::Aarch64UnknownLinuxGnu, "aarch64-linux"),
+    ]
+    .into_iter()
+    .filter(|(target, _)| targets.contains(target))
+    .collect()
+}
+
+fn get_build(info: &AppConfig) -> Build {
+    info.nix
+        .as_ref()
+        .and_then(|nix| nix.build)
+        .unwrap_or_default()
+}
+
+fn get_cargo_hash(info: &AppConfig) -> Option<String> {
+    info.nix.as_ref().and_then(|nix| nix.cargo_hash.clone())
+}
+
+fn flake_header(info: &AppConfig, description: &str) -> Vec<String> {
+    let nixpkgs = get_nixpkgs(info);
+
+    vec![
+        format!("{{"),
+        format!("  description = {description:?};"),
+        format!(""),
+        format!("  inputs = {{"),
+        format!("    nixpkgs.url = {nixpkgs:?};"),
+        format!("    flake-utils.url = \"github:numtide/flake-utils\";"),
+        format!("  }};"),
+        format!(""),
+        format!("  outputs = {{ self, nixpkgs, flake-utils }}:"),
+        format!("    with flake-utils.lib;"),
+        format!("    with nixpkgs.lib;"),
+        format!(""),
+    ]
+}
+
+fn indent(lines: &[String], width: usize) -> Vec<String> {
+    lines
+        .iter()
+        .map(|line| format!("{:width$}{line}", "").trim_end().to_string())
+        .collect()
+}
+
+// Finds the files in the repository matching the `path` pattern, named by what `%n` matched
+fn get_packages(root: &Path, dir: &Path, pattern: &str) -> Result<Vec<(String, String)>> {
+    let mut packages = vec![];
+
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            if !path.ends_with(".git") {
+                packages.extend(get_packages(root, &path, pattern)?);
+            }
+
+            continue;
+        }
+
+        let relative = path
+            .strip_prefix(root)
+            .unwrap()
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        let mut parts = pattern.split("%n");
+        let prefix = parts.next().unwrap_or_default();
+        let suffix = parts.next().unwrap_or_default();
+
+        let Some(rest) = relative.strip_prefix(prefix) else {
+            continue;
+        };
+
+        let name = match rest.find(suffix) {
+            Some(end) if !suffix.is_empty() => &rest[..end],
+            _ => rest,
+        };
+
+        if name.is_empty() || name.contains('/') || pattern.replace("%n", name) != relative {
+            continue;
+        }
+
+        // The generated flake can match the pattern too, and only the package files written by
+        // publisher can be imported with `{ pkgs }`
+        if relative == "flake.nix" || !read_to_string(&path)?.starts_with("{ pkgs }:") {
+            continue;
+        }
+
+        packages.push((name.to_string(), relative.clone()));
+    }
+
+    packages.sort();
+
+    Ok(packages)
+}
+
+fn get_apps(info: &AppConfig) -> bool {
+    info.nix.as_ref().and_then(|nix| nix.apps).unwrap_or(true)
+}
+
+fn get_overlay(info: &AppConfig) -> bool {
+    info.nix
+        .as_ref()
+        .and_then(|nix| nix.overlay)
+        .unwrap_or(true)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::{create_dir_all, write};
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn packages_matching_path() {
+        let dir = tempdir().unwrap();
+
+        for (file, content) in [
+            ("flake.nix", "{ pkgs }:"),
+            ("shell.nix", "{ pkgs ? import <nixpkgs> {} }:"),
+            ("baz.nix", "{ pkgs }:"),
+            ("pkgs/foo.nix", "{ pkgs }:"),
+            ("pkgs/bar.nix", "{ pkgs }:"),
+            ("pkgs/lib/util.nix", "{ pkgs }:"),
+            ("pkgs/overlay.nix", "final: prev: {}"),
+            ("modules/foo.nix", "{ pkgs }:"),
+        ] {
+            let path = dir.path().join(file);
+            create_dir_all(path.parent().unwrap()).unwrap();
+            write(path, content).unwrap();
+        }
+
+        assert_eq!(
+            get_packages(dir.path(), dir.path(), "pkgs/%n.nix").unwrap(),
+            vec![
+                ("bar".into(), "pkgs/bar.nix".into()),
+                ("foo".into(), "pkgs/foo.nix".into()),
+            ]
+        );
+
+        assert_eq!(
+            get_packages(dir.path(), dir.path(), "%n.nix").unwrap(),
+            vec![("baz".into(), "baz.nix".into())]
+        );
+    }
+}
@@ -1,18 +1,48 @@
+use std::{env::var, fs::read_to_string, path::Path, thread::sleep, time::Duration};
+
+use eyre::eyre;
+use owo_colors::OwoColorize;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, from_str, to_string_pretty};
+use tracing::info;
 use xshell::{Shell, cmd};
 
 use crate::{
     check::{CheckResults, check_program},
     config::AppConfig,
     error::Result,
-    publish::{download_binary, prepare_tmp_dir, write_file},
-    repositories::Repository,
+    publish::{PublishOptions, download_binary, prepare_tmp_dir, write_file},
+    repositories::{Repository, get_binaries, get_targets},
     targets::Target,
+    version::is_prerelease,
 };
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct NPMConfig {
     pub name: Option<String>,
+    pub registry: Option<String>,
+    pub tag: Option<String>,
+    pub prerelease_tag: Option<String>,
+    pub access: Option<String>,
+    pub verify: Option<bool>,
+    pub postinstall: Option<bool>,
+    pub detect_libc: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PackResult {
+    name: String,
+    version: String,
+    filename: String,
+    integrity: String,
+    files: Vec<PackFile>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PackFile {
+    path: String,
+    size: u64,
+    mode: u32,
 }
 
 #[allow(clippy::upper_case_acronyms)]
@@ -36,7 +66,21 @@ impl Repository for NPM {
         Ok(())
     }
 
-    fn publish(&self, info: &AppConfig, version: &str, dry_run: bool) -> Result {
+    fn targets(&self, info: &AppConfig) -> Vec<Target> {
+        get_targets(info)
+            .into_iter()
+            .filter(|target| target.npm().is_some())
+            .collect()
+    }
+
+    fn publish(
+        &self,
+        info: &AppConfig,
+        version: &str,
+        dry_run: bool,
+        output_dir: &Path,
+        options: &PublishOptions,
+    ) -> Result {
         let AppConfig {
             name: cli_name,
             description,
@@ -47,97 +91,191 @@ impl Repository for NPM {
         } = info;
 
         let name = get_name(info);
+        let binaries = get_binaries(info);
+        let access = get_access(info);
+        let registry = format!("{}/", get_registry(info).trim_end_matches('/'));
+        let npmrc = get_npmrc(info, &name);
 
-        let (sh, dir) = prepare_tmp_dir(self)?;
+        let mut publish_args = vec![format!("--tag"), get_tag(info, version)];
 
-        write_file(&dir, "main/package.json", || {
-            vec![
-                format!("{{"),
-                format!("  \"name\": {name:?},"),
-                format!("  \"version\": {version:?},"),
-                format!("  \"description\": {description:?},"),
-                format!("  \"homepage\": {homepage:?},"),
-                format!("  \"license\": {license:?},"),
-                format!("  \"repository\": {{"),
-                format!("    \"type\": \"git\","),
-                format!("    \"url\": \"git+https://github.com/{repository}.git\""),
-                format!("  }},"),
-                format!("  \"bin\": {{"),
-                format!("    {cli_name:?}: \"cli.js\""),
-                format!("  }},"),
+        if let Some(otp) = &options.otp {
+            publish_args.extend([format!("--otp"), otp.clone()]);
+        }
+
+        if options.provenance {
+            publish_args.push(format!("--provenance"));
+        }
+
+        let publish_args = &publish_args;
+
+        // Platform specific packages along with the platform as detected by node
+        let platforms = self
+            .targets(info)
+            .into_iter()
+            .map(|target| {
+                let (os, cpu, libc) = target.npm().unwrap();
+                let key = [Some(os), Some(cpu), libc]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>()
+                    .join("-");
+                let suffix = key.replacen("win32", "windows", 1);
+
+                (target, key, suffix)
+            })
+            .collect::<Vec<_>>();
+
+        let optional_dependencies = platforms
+            .iter()
+            .map(|(_, _, suffix)| format!("    \"{name}-{suffix}\": {version:?}"))
+            .collect::<Vec<_>>()
+            .join(",\n");
+
+        let distribution_packages = platforms
+            .iter()
+            .map(|(_, key, suffix)| format!("  {key:?}: \"{name}-{suffix}\","))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let (sh, dir) = prepare_tmp_dir(self, output_dir)?;
+
+        let bin = binaries
+            .iter()
+            .map(|binary| format!("    {binary:?}: \"bin/{binary}.js\""))
+            .collect::<Vec<_>>()
+            .join(",\n");
+
+        let npm = info.npm.as_ref();
+        let postinstall = npm.and_then(|npm| npm.postinstall).unwrap_or(true);
+        let detect_libc = npm.and_then(|npm| npm.detect_libc).unwrap_or(true);
+
+        let mut main_extra = vec![];
+
+        // Without these, the binary is resolved and the libc detected when the CLI is first run
+        if postinstall {
+            main_extra.extend([
                 format!("  \"scripts\": {{"),
                 format!("    \"postinstall\": \"node ./install.js\""),
                 format!("  }},"),
+            ]);
+        }
+
+        if detect_libc {
+            main_extra.extend([
                 format!("  \"dependencies\": {{"),
                 format!("    \"detect-libc\": \"^2.0.4\""),
                 format!("  }},"),
-                format!("  \"optionalDependencies\": {{"),
-                format!("    \"{name}-darwin-arm64\": {version:?},"),
-                format!("    \"{name}-darwin-x64\": {version:?},"),
-                format!("    \"{name}-linux-x64-glibc\": {version:?},"),
-                format!("    \"{name}-linux-ia32-glibc\": {version:?},"),
-                format!("    \"{name}-linux-x64-musl\": {version:?},"),
-                format!("    \"{name}-windows-x64\": {version:?},"),
-                format!("    \"{name}-windows-ia32\": {version:?}"),
-                format!("  }},"),
-                format!("  \"publishConfig\": {{"),
-                format!("    \"access\": \"public\""),
-                format!("  }}"),
-                format!("}}"),
+            ]);
+        }
+
+        write_file(&dir, "main/package.json", || {
+            [
+                vec![
+                    format!("{{"),
+                    format!("  \"name\": {name:?},"),
+                    format!("  \"version\": {version:?},"),
+                    format!("  \"description\": {description:?},"),
+                    format!("  \"homepage\": {homepage:?},"),
+                    format!("  \"license\": {license:?},"),
+                    format!("  \"repository\": {{"),
+                    format!("    \"type\": \"git\","),
+                    format!("    \"url\": \"git+https://github.com/{repository}.git\""),
+                    format!("  }},"),
+                    format!("  \"bin\": {{"),
+                    bin,
+                    format!("  }},"),
+                ],
+                main_extra,
+                vec![
+                    format!("  \"optionalDependencies\": {{"),
+                    optional_dependencies,
+                    format!("  }},"),
+                    format!("  \"publishConfig\": {{"),
+                    format!("    \"access\": {access:?}"),
+                    format!("  }}"),
+                    format!("}}"),
+                ],
             ]
+            .concat()
         })?;
 
+        write_file(&dir, "main/.npmrc", || npmrc.clone())?;
+
         write_file(&dir, "main/constants.js", || {
             vec![
                 format!("const BINARY_DISTRIBUTION_PACKAGES = {{"),
-                format!("  \"darwin-arm64\": \"{name}-darwin-arm64\","),
-                format!("  \"darwin-x64\": \"{name}-darwin-x64\","),
-                format!("  \"linux-x64-glibc\": \"{name}-linux-x64-glibc\","),
-                format!("  \"linux-ia32-glibc\": \"{name}-linux-ia32-glibc\","),
-                format!("  \"linux-x64-musl\": \"{name}-linux-x64-musl\","),
-                format!("  \"win32-x64\": \"{name}-windows-x64\","),
-                format!("  \"win32-ia32\": \"{name}-windows-ia32\","),
+                distribution_packages,
                 format!("}};"),
                 format!(""),
+                format!("const BINARY_DISTRIBUTION_REGISTRY = {registry:?};"),
+                format!(""),
                 format!("const BINARY_DISTRIBUTION_VERSION = {version:?};"),
                 format!(""),
-                format!(
-                    "const BINARY_NAME = [\"win32\", \"cygwin\"].includes(process.platform) ? \"{cli_name}.exe\" : {cli_name:?};"
-                ),
+                format!("const BINARY_NAMES = {binaries:?};"),
                 format!(""),
                 format!("module.exports = {{"),
                 format!("  BINARY_DISTRIBUTION_PACKAGES,"),
+                format!("  BINARY_DISTRIBUTION_REGISTRY,"),
                 format!("  BINARY_DISTRIBUTION_VERSION,"),
-                format!("  BINARY_NAME,"),
+                format!("  BINARY_NAMES,"),
                 format!("}};"),
             ]
         })?;
 
-        write_file(&dir, "main/install.js", || {
-            vec![include_str!("../templates/npm/install.js").into()]
-        })?;
+        if postinstall {
+            write_file(&dir, "main/install.js", || {
+                vec![include_str!("../templates/npm/install.js").into()]
+            })?;
+        }
 
         write_file(&dir, "main/binary.js", || {
             vec![include_str!("../templates/npm/binary.js").into()]
         })?;
 
-        write_file(&dir, "main/cli.js", || {
-            vec![include_str!("../templates/npm/cli.js").into()]
-        })?;
+        for binary in &binaries {
+            write_file(&dir, format!("main/bin/{binary}.js"), || {
+                vec![include_str!("../templates/npm/cli.js").into()]
+            })?;
+        }
+
+        // Skips the packages which were published by an earlier attempt
+        let publish = |package_dir: &str, package: &str, tarball: &str| -> Result {
+            sh.change_dir(Path::new(&dir).join(package_dir));
+
+            if is_published(&sh, package, version) {
+                info!("  {:>11} {}", "skipping".yellow(), package.cyan());
+                return Ok(());
+            }
+
+            info!("  {:>11} {}", "publishing".magenta(), package.cyan());
+
+            cmd!(sh, "npm publish {tarball} {publish_args...}")
+                .quiet()
+                .ignore_stderr()
+                .ignore_stdout()
+                .run()?;
+
+            // The registry might take a moment before serving the new version
+            for _ in 0..5 {
+                if is_published(&sh, package, version) {
+                    return Ok(());
+                }
 
-        let write_and_publish = |target: Target,
-                                 os: &str,
-                                 cpu: &str,
-                                 libc: Option<&str>|
-         -> Result {
-            let mut suffix = format!("{os}-{cpu}");
-            let mut libc_lines = String::new();
-
-            if let Some(libc) = libc {
-                suffix = format!("{suffix}-{libc}");
-                libc_lines = format!("  \"libc\": [{libc:?}],",);
+                sleep(Duration::from_secs(2));
             }
 
+            Err(eyre!(
+                "Unable to verify that {package}@{version} was published"
+            ))
+        };
+
+        let write_platform = |target: &Target, suffix: &str| -> Result {
+            let (os, cpu, libc) = target.npm().unwrap();
+
+            let libc_lines = libc
+                .map(|libc| format!("  \"libc\": [{libc:?}],"))
+                .unwrap_or_default();
+
             write_file(&dir, format!("{suffix}/package.json"), || {
                 vec![
                     format!("{{"),
@@ -154,12 +292,14 @@ impl Repository for NPM {
                     format!("  \"cpu\": [{cpu:?}],"),
                     libc_lines,
                     format!("  \"publishConfig\": {{"),
-                    format!("    \"access\": \"public\""),
+                    format!("    \"access\": {access:?}"),
                     format!("  }}"),
                     format!("}}"),
                 ]
             })?;
 
+            write_file(&dir, format!("{suffix}/.npmrc"), || npmrc.clone())?;
+
             download_binary(
                 &sh,
                 &dir,
@@ -167,36 +307,58 @@ impl Repository for NPM {
                 &format!(
                     "https://github.com/{repository}/releases/download/v{version}/{cli_name}-v{version}-{target}.zip"
                 ),
-            )?;
-
-            if !dry_run {
-                sh.change_dir(format!("../{suffix}"));
-                cmd!(sh, "npm publish")
-                    .quiet()
-                    .ignore_stderr()
-                    .ignore_stdout()
-                    .run()?;
-            }
-
-            Ok(())
+            )
         };
 
-        if !dry_run {
-            sh.change_dir("main");
-            cmd!(sh, "npm publish")
-                .quiet()
-                .ignore_stderr()
-                .ignore_stdout()
-                .run()?;
+        let mut packages = vec![];
+
+        for (target, _, suffix) in &platforms {
+            write_platform(target, suffix)?;
+
+            let (os, _, _) = target.npm().unwrap();
+            let files = binaries
+                .iter()
+                .map(|binary| match os {
+                    "win32" => format!("bin/{binary}.exe"),
+                    _ => format!("bin/{binary}"),
+                })
+                .collect::<Vec<_>>();
+
+            sh.change_dir(Path::new(&dir).join(suffix));
+            let package = format!("{name}-{suffix}");
+            let result = pack(&sh, info, &package, version, &files, os != "win32")?;
+
+            packages.push((suffix.clone(), package, result));
+        }
+
+        // The main package goes last so that it never points to missing platform packages
+        sh.change_dir(Path::new(&dir).join("main"));
+        let files = binaries
+            .iter()
+            .map(|binary| format!("bin/{binary}.js"))
+            .collect::<Vec<_>>();
+        let result = pack(&sh, info, &name, version, &files, false)?;
+
+        packages.push((format!("main"), name.clone(), result));
+
+        if dry_run {
+            for (package_dir, package, _) in &packages {
+                print_package_diff(&sh, &dir, package_dir, package)?;
+            }
+        } else {
+            for (package_dir, package, result) in &packages {
+                publish(package_dir, package, &result.filename)?;
+            }
         }
 
-        write_and_publish(Target::Aarch64AppleDarwin, "darwin", "arm64", None)?;
-        write_and_publish(Target::X86_64AppleDarwin, "darwin", "x64", None)?;
-        write_and_publish(Target::X86_64UnknownLinuxGnu, "linux", "x64", Some("glibc"))?;
-        write_and_publish(Target::I686UnknownLinuxGnu, "linux", "ia32", Some("glibc"))?;
-        write_and_publish(Target::X86_64UnknownLinuxMusl, "linux", "x64", Some("musl"))?;
-        write_and_publish(Target::X86_64PcWindowsMsvc, "windows", "x64", None)?;
-        write_and_publish(Target::I686PcWindowsMsvc, "windows", "ia32", None)?;
+        for (_, package, result) in &packages {
+            info!(
+                "  {:>11} {} {}",
+                "integrity".magenta(),
+                package.cyan(),
+                result.integrity
+            );
+        }
 
         Ok(())
     }
@@ -214,6 +376,191 @@ impl Repository for NPM {
     }
 }
 
+// Pre-releases are not published as `latest` so that they are only installed on request
+fn get_tag(info: &AppConfig, version: &str) -> String {
+    let npm = info.npm.as_ref();
+
+    if !is_prerelease(version) {
+        return npm
+            .and_then(|npm| npm.tag.clone())
+            .unwrap_or_else(|| "latest".into());
+    }
+
+    npm.and_then(|npm| npm.prerelease_tag.clone())
+        .unwrap_or_else(|| "next".into())
+}
+
+// Packs the package and verifies its contents so that broken packages are not published
+fn pack(
+    sh: &Shell,
+    info: &AppConfig,
+    package: &str,
+    version: &str,
+    files: &[String],
+    executable: bool,
+) -> Result<PackResult> {
+    let output = cmd!(sh, "npm pack --json").quiet().ignore_stderr().read()?;
+
+    let result = from_str::<Vec<PackResult>>(&output)?
+        .pop()
+        .ok_or(eyre!("Unable to pack {package}"))?;
+
+    if !info.npm.as_ref().and_then(|npm| npm.verify).unwrap_or(true) {
+        return Ok(result);
+    }
+
+    let mut errors = vec![];
+
+    if result.name != package {
+        errors.push(format!("name is {} instead of {package}", result.name));
+    }
+
+    if result.version != version {
+        errors.push(format!(
+            "version is {} instead of {version}",
+            result.version
+        ));
+    }
+
+    let manifest = from_str::<Value>(&read_to_string(sh.current_dir().join("package.json"))?)?;
+
+    for (field, expected) in [
+        ("description", &info.description),
+        ("homepage", &info.homepage),
+        ("license", &info.license),
+    ] {
+        if manifest[field].as_str() != Some(expected.as_str()) {
+            errors.push(format!("{field} does not match the configuration"));
+        }
+    }
+
+    for file in files {
+        match result.files.iter().find(|f| &f.path == file) {
+            None => errors.push(format!("{file} is missing")),
+            Some(f) if f.size == 0 => errors.push(format!("{file} is empty")),
+            Some(f) if executable && f.mode & 0o111 == 0 => {
+                errors.push(format!("{file} is not executable"))
+            }
+            _ => {}
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(eyre!(
+            "Package {package} is not valid: {}",
+            errors.join(", ")
+        ));
+    }
+
+    Ok(result)
+}
+
+// Compares the generated `package.json` with the fields of the latest published version
+fn print_package_diff(sh: &Shell, dir: &str, package_dir: &str, package: &str) -> Result {
+    sh.change_dir(dir);
+
+    let Ok(published) = cmd!(sh, "npm view {package} --json")
+        .quiet()
+        .ignore_stderr()
+        .read()
+    else {
+        info!("  {:>11} {}", "new".magenta(), package.cyan());
+        return Ok(());
+    };
+
+    let published = from_str::<Value>(&published)?;
+    let generated = from_str::<Value>(&read_to_string(
+        Path::new(dir).join(package_dir).join("package.json"),
+    )?)?;
+
+    // The registry does not keep `publishConfig`, so only the other fields are compared
+    let fields = generated
+        .as_object()
+        .into_iter()
+        .flat_map(|fields| fields.keys())
+        .filter(|field| *field != "publishConfig")
+        .map(|field| (field.clone(), published[field].clone()))
+        .filter(|(_, value)| !value.is_null())
+        .collect::<Map<_, _>>();
+
+    // Both are written with sorted keys so that only the values show up in the diff
+    let published_path = format!("diff/published/{package_dir}/package.json");
+    let generated_path = format!("diff/generated/{package_dir}/package.json");
+
+    write_file(dir, &published_path, || {
+        vec![to_string_pretty(&Value::Object(fields)).unwrap()]
+    })?;
+    write_file(dir, &generated_path, || {
+        vec![to_string_pretty(&generated).unwrap()]
+    })?;
+
+    info!("  {:>11} {}", "comparing".magenta(), package.cyan());
+
+    // Exits with failure when the files differ
+    let diff = cmd!(
+        sh,
+        "git diff --no-index --no-ext-diff --color=always {published_path} {generated_path}"
+    )
+    .quiet()
+    .ignore_status()
+    .read()?;
+
+    if diff.is_empty() {
+        info!("  {:>11}", "unchanged".magenta());
+    } else {
+        println!("{diff}");
+    }
+
+    Ok(())
+}
+
+fn is_published(sh: &Shell, package: &str, version: &str) -> bool {
+    cmd!(sh, "npm view {package}@{version} version")
+        .quiet()
+        .ignore_stderr()
+        .read()
+        .is_ok_and(|output| output.trim() == version)
+}
+
+fn get_registry(info: &AppConfig) -> String {
+    info.npm
+        .as_ref()
+        .and_then(|npm| npm.registry.clone())
+        .unwrap_or_else(|| "https://registry.npmjs.org/".into())
+}
+
+fn get_access(info: &AppConfig) -> String {
+    info.npm
+        .as_ref()
+        .and_then(|npm| npm.access.clone())
+        .unwrap_or_else(|| "public".into())
+}
+
+// Written next to each package so that no global npm configuration is needed
+fn get_npmrc(info: &AppConfig, name: &str) -> Vec<String> {
+    let registry = get_registry(info);
+    let mut lines = vec![];
+
+    match name.split_once('/') {
+        Some((scope, _)) if scope.starts_with('@') => {
+            lines.push(format!("{scope}:registry={registry}"))
+        }
+        _ => lines.push(format!("registry={registry}")),
+    }
+
+    // Only use the token from environment when available so that an existing login keeps working
+    if var("NODE_AUTH_TOKEN").is_ok() {
+        let host = registry
+            .split_once("://")
+            .map_or(registry.as_str(), |(_, host)| host)
+            .trim_end_matches('/');
+
+        lines.push(format!("//{host}/:_authToken=${{NODE_AUTH_TOKEN}}"));
+    }
+
+    lines
+}
+
 fn get_name(info: &AppConfig) -> String {
     info.npm
         .as_ref()
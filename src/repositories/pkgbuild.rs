@@ -0,0 +1,569 @@
+use std::{collections::HashMap, fs::read_to_string, path::Path};
+
+use eyre::eyre;
+use reqwest::blocking::get;
+
+use super::{Completion, get_binaries, get_completions, get_manpages, get_systemd_unit};
+use crate::{check::CheckResults, config::AppConfig, error::Result};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct Pkgbuild {
+    pub pkgname: String,
+    pub pkgver: String,
+    pub pkgrel: u32,
+    pub pkgdesc: String,
+    pub arch: Vec<String>,
+    pub url: String,
+    pub license: Vec<String>,
+    pub makedepends: Vec<String>,
+    pub depends: Vec<String>,
+    pub optdepends: Vec<String>,
+    pub provides: Vec<String>,
+    pub conflicts: Vec<String>,
+    pub sources: Vec<Source>,
+    pub functions: Vec<(String, Vec<String>)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct Source {
+    pub arch: Option<String>,
+    pub source: String,
+    pub sha256sum: String,
+}
+
+impl Pkgbuild {
+    pub fn to_pkgbuild(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("pkgname={}", self.pkgname),
+            format!("pkgver={}", self.pkgver),
+            format!("pkgrel={}", self.pkgrel),
+            format!("pkgdesc={}", quote(&self.pkgdesc)),
+            format!("arch={}", array(&self.arch)),
+            format!("url={}", quote(&self.url)),
+            format!("license={}", array(&self.license)),
+        ];
+
+        for (key, values) in self.lists() {
+            if !values.is_empty() {
+                lines.push(format!("{key}={}", array(values)));
+            }
+        }
+
+        for (arch, sources) in self.sources_per_arch() {
+            let suffix = arch.map(|a| format!("_{a}")).unwrap_or_default();
+
+            let (source, sha256sums): (Vec<_>, Vec<_>) = sources
+                .into_iter()
+                .map(|s| (s.source.clone(), s.sha256sum.clone()))
+                .unzip();
+
+            lines.push(format!("source{suffix}={}", array(&source)));
+            lines.push(format!("sha256sums{suffix}={}", array(&sha256sums)));
+        }
+
+        for (name, body) in &self.functions {
+            lines.push(format!(""));
+            lines.push(format!("{name}() {{"));
+            lines.extend(body.iter().cloned());
+            lines.push(format!("}}"));
+        }
+
+        lines
+    }
+
+    pub fn to_srcinfo(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("pkgbase = {}", self.pkgname),
+            format!("\tpkgdesc = {}", self.pkgdesc),
+            format!("\tpkgver = {}", self.pkgver),
+            format!("\tpkgrel = {}", self.pkgrel),
+            format!("\turl = {}", self.url),
+        ];
+
+        lines.extend(self.arch.iter().map(|a| format!("\tarch = {a}")));
+        lines.extend(self.license.iter().map(|l| format!("\tlicense = {l}")));
+
+        for (key, values) in self.lists() {
+            lines.extend(values.iter().map(|v| format!("\t{key} = {v}")));
+        }
+
+        for (arch, sources) in self.sources_per_arch() {
+            let suffix = arch.map(|a| format!("_{a}")).unwrap_or_default();
+
+            lines.extend(
+                sources
+                    .iter()
+                    .map(|s| format!("\tsource{suffix} = {}", s.source)),
+            );
+            lines.extend(
+                sources
+                    .iter()
+                    .map(|s| format!("\tsha256sums{suffix} = {}", s.sha256sum)),
+            );
+        }
+
+        lines.push(format!(""));
+        lines.push(format!("pkgname = {}", self.pkgname));
+
+        lines
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut variables = HashMap::<String, Vec<String>>::new();
+        let mut functions = vec![];
+        let mut lines = content.lines();
+
+        while let Some(line) = lines.next() {
+            let line = line.trim_end();
+
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_suffix("() {") {
+                let body = lines
+                    .by_ref()
+                    .take_while(|l| *l != "}")
+                    .map(String::from)
+                    .collect();
+
+                functions.push((name.trim().to_string(), body));
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| eyre!("Unable to parse PKGBUILD line: {line}"))?;
+
+            let mut value = value.to_string();
+
+            // Arrays can span multiple lines
+            if value.starts_with('(') {
+                while !value.trim_end().ends_with(')') {
+                    let next = lines
+                        .next()
+                        .ok_or_else(|| eyre!("Unterminated array in PKGBUILD: {key}"))?;
+                    value = format!("{value} {}", next.trim());
+                }
+
+                value = value.trim()[1..value.trim().len() - 1].to_string();
+            }
+
+            let values = split_words(&value, &variables)?;
+
+            variables.insert(key.trim().to_string(), values);
+        }
+
+        let mut pkgbuild = Self::from_variables(variables)?;
+        pkgbuild.functions = functions;
+
+        Ok(pkgbuild)
+    }
+
+    pub fn parse_srcinfo(content: &str) -> Result<Self> {
+        let mut variables = HashMap::<String, Vec<String>>::new();
+
+        for line in content.lines() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once(" = ")
+                .ok_or_else(|| eyre!("Unable to parse .SRCINFO line: {line}"))?;
+
+            let key = match key {
+                "pkgbase" => "pkgname",
+                // We only support a single package in the base
+                "pkgname" => continue,
+                key => key,
+            };
+
+            variables
+                .entry(key.to_string())
+                .or_default()
+                .push(value.to_string());
+        }
+
+        Self::from_variables(variables)
+    }
+
+    fn from_variables(mut variables: HashMap<String, Vec<String>>) -> Result<Self> {
+        let mut take = |key: &str| variables.remove(key).unwrap_or_default();
+
+        let pkgname = take("pkgname").concat();
+        let pkgver = take("pkgver").concat();
+        let pkgrel = take("pkgrel").concat();
+        let pkgdesc = take("pkgdesc").concat();
+        let url = take("url").concat();
+
+        let pkgrel = pkgrel
+            .parse()
+            .map_err(|_| eyre!("Invalid pkgrel: {pkgrel:?}"))?;
+
+        let arch = take("arch");
+        let mut sources = vec![];
+
+        for arch in [None].into_iter().chain(arch.iter().map(Some)) {
+            let suffix = arch.map(|a| format!("_{a}")).unwrap_or_default();
+
+            let source = take(&format!("source{suffix}"));
+            let sha256sums = take(&format!("sha256sums{suffix}"));
+
+            if source.len() != sha256sums.len() {
+                return Err(eyre!(
+                    "Number of sources{suffix} and sha256sums{suffix} differ"
+                ));
+            }
+
+            sources.extend(
+                source
+                    .into_iter()
+                    .zip(sha256sums)
+                    .map(|(source, sha256sum)| Source {
+                        arch: arch.cloned(),
+                        source,
+                        sha256sum,
+                    }),
+            );
+        }
+
+        Ok(Self {
+            pkgname,
+            pkgver,
+            pkgrel,
+            pkgdesc,
+            url,
+            license: take("license"),
+            makedepends: take("makedepends"),
+            depends: take("depends"),
+            optdepends: take("optdepends"),
+            provides: take("provides"),
+            conflicts: take("conflicts"),
+            arch,
+            sources,
+            functions: vec![],
+        })
+    }
+
+    fn lists(&self) -> [(&'static str, &Vec<String>); 5] {
+        [
+            ("makedepends", &self.makedepends),
+            ("depends", &self.depends),
+            ("optdepends", &self.optdepends),
+            ("provides", &self.provides),
+            ("conflicts", &self.conflicts),
+        ]
+    }
+
+    fn sources_per_arch(&self) -> Vec<(Option<&String>, Vec<&Source>)> {
+        let mut grouped: Vec<(Option<&String>, Vec<&Source>)> = vec![];
+
+        for source in &self.sources {
+            let arch = source.arch.as_ref();
+
+            match grouped.iter_mut().find(|(a, _)| *a == arch) {
+                Some((_, sources)) => sources.push(source),
+                None => grouped.push((arch, vec![source])),
+            }
+        }
+
+        grouped
+    }
+}
+
+// Hyphens are not allowed in pkgver, which pre-release versions have
+pub(super) fn get_pkgver(version: &str) -> String {
+    version.replace('-', "_")
+}
+
+pub(super) fn get_pkgrel(dir: &str, version: &str, pkgrel: Option<u32>) -> Result<u32> {
+    if let Some(pkgrel) = pkgrel {
+        return Ok(pkgrel);
+    }
+
+    // Continue the release number of the existing package if the version is unchanged
+    let Ok(content) = read_to_string(Path::new(dir).join("PKGBUILD")) else {
+        return Ok(1);
+    };
+
+    let existing = Pkgbuild::parse(&content).map_err(|e| {
+        eyre!("Unable to read pkgrel of the existing PKGBUILD, pass it with --pkgrel: {e}")
+    })?;
+
+    Ok(if existing.pkgver == get_pkgver(version) {
+        existing.pkgrel + 1
+    } else {
+        1
+    })
+}
+
+pub(super) fn check_pkgbuild(results: &mut CheckResults, name: &str) -> Result {
+    let fetch = |file: &str| -> Result<Option<String>> {
+        let response = get(format!(
+            "https://aur.archlinux.org/cgit/aur.git/plain/{file}?h={name}"
+        ))?;
+
+        Ok(response
+            .status()
+            .is_success()
+            .then(|| response.text())
+            .transpose()?)
+    };
+
+    let (Some(pkgbuild), Some(srcinfo)) = (fetch("PKGBUILD")?, fetch(".SRCINFO")?) else {
+        // Nothing to validate for a package that is not published yet
+        return Ok(());
+    };
+
+    let error = match (
+        Pkgbuild::parse(&pkgbuild),
+        Pkgbuild::parse_srcinfo(&srcinfo),
+    ) {
+        (Err(e), _) => Some(format!("unable to parse PKGBUILD: {e}")),
+        (_, Err(e)) => Some(format!("unable to parse .SRCINFO: {e}")),
+        (Ok(pkgbuild), _) if pkgbuild.pkgname != name => Some(format!(
+            "PKGBUILD has pkgname '{}' instead of '{name}'",
+            pkgbuild.pkgname
+        )),
+        (Ok(mut pkgbuild), Ok(srcinfo)) => {
+            pkgbuild.functions.clear();
+            (pkgbuild != srcinfo).then(|| format!(".SRCINFO does not match PKGBUILD"))
+        }
+    };
+
+    results.add_result("pkgbuild", error);
+
+    Ok(())
+}
+
+pub(super) fn install_binaries(info: &AppConfig, dir: &str) -> Vec<String> {
+    get_binaries(info)
+        .into_iter()
+        .map(|binary| format!("    install -Dm755 \"{dir}{binary}\" \"$pkgdir/usr/bin/{binary}\""))
+        .collect()
+}
+
+// Lines for the package function which install completions and man pages
+pub(super) fn install_shell_files(info: &AppConfig) -> Vec<String> {
+    let cli_name = &info.name;
+
+    let completions = get_completions(info)
+        .into_iter()
+        .map(|(shell, completion)| {
+            let dest = match shell {
+                "bash" => format!("$pkgdir/usr/share/bash-completion/completions/{cli_name}"),
+                "zsh" => format!("$pkgdir/usr/share/zsh/site-functions/_{cli_name}"),
+                _ => format!("$pkgdir/usr/share/fish/vendor_completions.d/{cli_name}.fish"),
+            };
+
+            match completion {
+                Completion::File(path) => format!("    install -Dm644 \"{path}\" \"{dest}\""),
+                Completion::Command(command) => format!(
+                    "    \"$pkgdir/usr/bin/{cli_name}\" {command} {shell} | install -Dm644 /dev/stdin \"{dest}\""
+                ),
+            }
+        });
+
+    let manpages = get_manpages(info)
+        .into_iter()
+        .map(|(path, section, file_name)| {
+            format!(
+                "    install -Dm644 \"{path}\" \"$pkgdir/usr/share/man/man{section}/{file_name}\""
+            )
+        });
+
+    completions.chain(manpages).collect()
+}
+
+pub(super) fn install_service(info: &AppConfig) -> Vec<String> {
+    let unit = get_systemd_unit(info, "/usr/bin");
+
+    if unit.is_empty() {
+        return unit;
+    }
+
+    [
+        vec![format!(
+            "    install -Dm644 /dev/stdin \"$pkgdir/usr/lib/systemd/user/{}.service\" <<'EOF'",
+            info.name
+        )],
+        unit,
+        vec![format!("EOF")],
+    ]
+    .concat()
+}
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn array(values: &[String]) -> String {
+    format!(
+        "({})",
+        values
+            .iter()
+            .map(|v| quote(v))
+            .collect::<Vec<_>>()
+            .join(" ")
+    )
+}
+
+// Splits a shell value into words while removing the quotes
+// Variables are expanded like the shell does, except in the single-quoted parts
+fn split_words(value: &str, variables: &HashMap<String, Vec<String>>) -> Result<Vec<String>> {
+    let mut words = vec![];
+    let mut word = None::<String>;
+    // Part of the current word that is not single-quoted and still needs expansion
+    let mut pending = String::new();
+    let mut chars = value.chars();
+
+    let flush = |word: &mut Option<String>, pending: &mut String| {
+        word.get_or_insert_with(String::new)
+            .push_str(&expand(pending, variables));
+        pending.clear();
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                flush(&mut word, &mut pending);
+                let word = word.get_or_insert_with(String::new);
+
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(n) => word.push(n),
+                        None => return Err(eyre!("Unterminated quote in: {value}")),
+                    }
+                }
+            }
+            '"' => {
+                word.get_or_insert_with(String::new);
+
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => pending.extend(chars.next()),
+                        Some(n) => pending.push(n),
+                        None => return Err(eyre!("Unterminated quote in: {value}")),
+                    }
+                }
+            }
+            '\\' => {
+                word.get_or_insert_with(String::new);
+                pending.extend(chars.next());
+            }
+            c if c.is_whitespace() => {
+                if word.is_some() || !pending.is_empty() {
+                    flush(&mut word, &mut pending);
+                }
+
+                words.extend(word.take());
+            }
+            c => pending.push(c),
+        }
+    }
+
+    if word.is_some() || !pending.is_empty() {
+        flush(&mut word, &mut pending);
+    }
+
+    words.extend(word);
+
+    Ok(words)
+}
+
+// Expands references to the previously assigned scalar variables
+fn expand(value: &str, variables: &HashMap<String, Vec<String>>) -> String {
+    let mut expanded = value.to_string();
+
+    for (key, values) in variables {
+        if let [v] = values.as_slice() {
+            expanded = expanded
+                .replace(&format!("${{{key}}}"), v)
+                .replace(&format!("${key}"), v);
+        }
+    }
+
+    expanded
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pkgbuild() -> Pkgbuild {
+        Pkgbuild {
+            pkgname: "publisher-bin".into(),
+            pkgver: "1.0.0".into(),
+            pkgrel: 2,
+            pkgdesc: "Tool to publish & distribute CLI tools".into(),
+            arch: vec!["x86_64".into(), "i686".into()],
+            url: "https://github.com/termapps/publisher".into(),
+            license: vec!["MIT".into()],
+            provides: vec!["publisher".into()],
+            conflicts: vec!["publisher".into()],
+            sources: vec![
+                Source {
+                    arch: Some("x86_64".into()),
+                    source: "publisher-1.0.0.zip::https://example.com/x86_64.zip".into(),
+                    sha256sum: "abc".into(),
+                },
+                Source {
+                    arch: Some("i686".into()),
+                    source: "publisher-1.0.0.zip::https://example.com/i686.zip".into(),
+                    sha256sum: "def".into(),
+                },
+            ],
+            functions: vec![(
+                "package".into(),
+                vec!["    install -Dm755 \"publisher\" \"$pkgdir/usr/bin/publisher\"".into()],
+            )],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let pkgbuild = pkgbuild();
+
+        let parsed = Pkgbuild::parse(&pkgbuild.to_pkgbuild().join("\n")).unwrap();
+        assert_eq!(parsed, pkgbuild);
+
+        let parsed = Pkgbuild::parse_srcinfo(&pkgbuild.to_srcinfo().join("\n")).unwrap();
+        assert_eq!(
+            parsed,
+            Pkgbuild {
+                functions: vec![],
+                ..pkgbuild
+            }
+        );
+    }
+
+    #[test]
+    fn parse_variables() {
+        let parsed = Pkgbuild::parse(
+            "pkgname=publisher\npkgver=1.0.0\npkgrel=0\nconflicts=()\nsource=($pkgname-$pkgver.zip::https://example.com/v${pkgver}.zip)\nsha256sums=(\"abc\")",
+        )
+        .unwrap();
+
+        assert_eq!(
+            parsed.sources[0].source,
+            "publisher-1.0.0.zip::https://example.com/v1.0.0.zip"
+        );
+    }
+
+    #[test]
+    fn parse_single_quoted() {
+        let parsed = Pkgbuild::parse(
+            "pkgname=publisher\npkgver=1.0.0\npkgrel=1\npkgdesc='A $pkgname tool'\nurl=\"$pkgname\"'-$pkgver'",
+        )
+        .unwrap();
+
+        assert_eq!(parsed.pkgdesc, "A $pkgname tool");
+        assert_eq!(parsed.url, "publisher-$pkgver");
+    }
+}
@@ -1,20 +1,29 @@
+use std::path::Path;
+
+use eyre::eyre;
 use serde::{Deserialize, Serialize};
 use xshell::Shell;
 
-use super::get_checksums;
+use super::{get_binaries, get_checksums, get_dependencies, get_targets};
 use crate::{
     check::{CheckResults, check_git, check_repo},
-    config::AppConfig,
+    config::{AppConfig, DependenciesConfig},
     error::Result,
-    publish::{commit_and_push, prepare_git_repo, write_and_add},
+    publish::{PublishOptions, commit_and_push, prepare_git_repo, print_diff, write_and_add},
     repositories::Repository,
     targets::Target,
+    version::is_prerelease,
 };
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct ScoopConfig {
     pub name: Option<String>,
     pub repository: Option<String>,
+    pub dependencies: Option<DependenciesConfig>,
+    pub notes: Option<String>,
+    pub persist: Option<Vec<String>>,
+    pub shortcuts: Option<Vec<Vec<String>>>,
+    pub env_add_path: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone)]
@@ -43,7 +52,21 @@ impl Repository for Scoop {
         Ok(())
     }
 
-    fn publish(&self, info: &AppConfig, version: &str, dry_run: bool) -> Result {
+    fn targets(&self, info: &AppConfig) -> Vec<Target> {
+        get_arches(info)
+            .into_iter()
+            .map(|(target, _)| target)
+            .collect()
+    }
+
+    fn publish(
+        &self,
+        info: &AppConfig,
+        version: &str,
+        dry_run: bool,
+        output_dir: &Path,
+        _: &PublishOptions,
+    ) -> Result {
         let AppConfig {
             name: cli_name,
             description,
@@ -55,47 +78,136 @@ impl Repository for Scoop {
 
         let name = get_name(info);
         let pkg_repo = get_repository(info);
-        let (sh, dir) = prepare_git_repo(self, &format!("git@github.com:{pkg_repo}"))?;
+        let (sh, dir) = prepare_git_repo(self, output_dir, &format!("git@github.com:{pkg_repo}"))?;
 
-        let checksums = get_checksums(
+        let arches = get_arches(info);
+
+        if arches.is_empty() {
+            return Err(eyre!("No windows targets are configured for Scoop"));
+        }
+
+        let checksums = get_checksums(info, version, self.targets(info))?;
+
+        let (required, optional) = get_dependencies(
             info,
-            version,
-            vec![Target::I686PcWindowsMsvc, Target::X86_64PcWindowsMsvc],
-        )?;
+            info.scoop
+                .as_ref()
+                .and_then(|scoop| scoop.dependencies.as_ref()),
+        );
+
+        let mut dependencies = vec![];
+
+        if !required.is_empty() {
+            dependencies.push(format!("  \"depends\": {required:?},"));
+        }
+
+        if !optional.is_empty() {
+            let suggest = optional
+                .iter()
+                .map(|d| format!("{d:?}: [{d:?}]"))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            dependencies.push(format!("  \"suggest\": {{ {suggest} }},"));
+        }
+
+        let binaries = get_binaries(info)
+            .iter()
+            .map(|b| format!("{b}.exe"))
+            .collect::<Vec<_>>();
+
+        let scoop = info.scoop.clone().unwrap_or_default();
+
+        let mut extra = vec![];
+
+        if let Some(shortcuts) = &scoop.shortcuts {
+            extra.push(format!("  \"shortcuts\": {shortcuts:?},"));
+        }
+
+        if let Some(persist) = &scoop.persist {
+            extra.push(format!("  \"persist\": {persist:?},"));
+        }
+
+        if let Some(env_add_path) = &scoop.env_add_path {
+            extra.push(format!("  \"env_add_path\": {env_add_path:?},"));
+        }
+
+        if let Some(notes) = &scoop.notes {
+            extra.push(format!(
+                "  \"notes\": {:?},",
+                notes.lines().collect::<Vec<_>>()
+            ));
+        }
+
+        let architecture = |version: &str, hash: &dyn Fn(&Target) -> String| {
+            let mut lines = vec![];
+
+            for (i, (target, arch)) in arches.iter().enumerate() {
+                let comma = if i + 1 < arches.len() { "," } else { "" };
+
+                lines.extend([
+                    format!("    \"{arch}\": {{"),
+                    format!(
+                        "      \"url\": \"https://github.com/{repository}/releases/download/v{version}/{cli_name}-v{version}-{target}.zip\","
+                    ),
+                    format!("      \"hash\": {}", hash(target)),
+                    format!("    }}{comma}"),
+                ]);
+            }
+
+            lines
+        };
+
+        // The `github` checkver only finds the latest stable release
+        let autoupdate = if is_prerelease(version) {
+            vec![]
+        } else {
+            [
+                vec![
+                    format!("  \"checkver\": {{"),
+                    format!("    \"github\": \"https://github.com/{repository}\""),
+                    format!("  }},"),
+                    format!("  \"autoupdate\": {{"),
+                    format!("    \"architecture\": {{"),
+                ],
+                architecture("$version", &|target| {
+                    format!(
+                        "{{ \"url\": \"https://github.com/{repository}/releases/download/v$version/{cli_name}-v$version-{target}_sha256sum.txt\" }}"
+                    )
+                })
+                .iter()
+                .map(|line| format!("  {line}"))
+                .collect(),
+                vec![format!("    }}"), format!("  }},")],
+            ]
+            .concat()
+        };
 
         write_and_add(&sh, &dir, format!("{name}.json"), || {
-            vec![
-                format!("{{"),
-                format!("  \"version\": {version:?},"),
-                format!("  \"description\": {description:?},"),
-                format!("  \"homepage\": {homepage:?},"),
-                format!("  \"license\": {license:?},"),
-                format!("  \"architecture\": {{"),
-                format!("    \"64bit\": {{"),
-                format!(
-                    "      \"url\": \"https://github.com/{repository}/releases/download/v{version}/{cli_name}-v{version}-x86_64-pc-windows-msvc.zip\","
-                ),
-                format!(
-                    "      \"hash\": {:?}",
-                    checksums.get(&Target::X86_64PcWindowsMsvc).unwrap()
-                ),
-                format!("    }},"),
-                format!("    \"32bit\": {{"),
-                format!(
-                    "      \"url\": \"https://github.com/{repository}/releases/download/v{version}/{cli_name}-v{version}-i686-pc-windows-msvc.zip\","
-                ),
-                format!(
-                    "      \"hash\": {:?}",
-                    checksums.get(&Target::I686PcWindowsMsvc).unwrap()
-                ),
-                format!("    }}"),
-                format!("  }},"),
-                format!("  \"bin\": [\"{cli_name}.exe\"]"),
-                format!("}}"),
+            [
+                vec![
+                    format!("{{"),
+                    format!("  \"version\": {version:?},"),
+                    format!("  \"description\": {description:?},"),
+                    format!("  \"homepage\": {homepage:?},"),
+                    format!("  \"license\": {license:?},"),
+                ],
+                dependencies,
+                vec![format!("  \"architecture\": {{")],
+                architecture(version, &|target| {
+                    format!("{:?}", checksums.get(target).unwrap())
+                }),
+                vec![format!("  }},")],
+                extra,
+                autoupdate,
+                vec![format!("  \"bin\": {binaries:?}"), format!("}}")],
             ]
+            .concat()
         })?;
 
-        if !dry_run {
+        if dry_run {
+            print_diff(&sh)?;
+        } else {
             commit_and_push(&sh, &name, version)?;
         }
 
@@ -118,7 +230,7 @@ impl Repository for Scoop {
     }
 }
 
-fn get_name(info: &AppConfig) -> String {
+pub(super) fn get_name(info: &AppConfig) -> String {
     info.scoop
         .as_ref()
         .and_then(|scoop| scoop.name.clone())
@@ -131,3 +243,17 @@ fn get_repository(info: &AppConfig) -> String {
         .and_then(|scoop| scoop.repository.clone())
         .unwrap_or_else(|| info.repository.clone())
 }
+
+// Scoop architectures of the configured targets
+fn get_arches(info: &AppConfig) -> Vec<(Target, &'static str)> {
+    let targets = get_targets(info);
+
+    [
+        (Target::X86_64PcWindowsMsvc, "64bit"),
+        (Target::I686PcWindowsMsvc, "32bit"),
+        (Target::Aarch64PcWindowsMsvc, "arm64"),
+    ]
+    .into_iter()
+    .filter(|(target, _)| targets.contains(target))
+    .collect()
+}
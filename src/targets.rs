@@ -1,14 +1,30 @@
 use std::fmt::Display;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub enum Target {
+    #[serde(rename = "aarch64-apple-darwin")]
     Aarch64AppleDarwin,
+    #[serde(rename = "x86_64-apple-darwin")]
     X86_64AppleDarwin,
+    #[serde(rename = "x86_64-unknown-linux-gnu")]
     X86_64UnknownLinuxGnu,
+    #[serde(rename = "i686-unknown-linux-gnu")]
     I686UnknownLinuxGnu,
+    #[serde(rename = "aarch64-unknown-linux-gnu")]
+    Aarch64UnknownLinuxGnu,
+    #[serde(rename = "x86_64-unknown-linux-musl")]
     X86_64UnknownLinuxMusl,
+    #[serde(rename = "aarch64-unknown-linux-musl")]
+    Aarch64UnknownLinuxMusl,
+    #[serde(rename = "x86_64-pc-windows-msvc")]
     X86_64PcWindowsMsvc,
+    #[serde(rename = "i686-pc-windows-msvc")]
     I686PcWindowsMsvc,
+    #[serde(rename = "aarch64-pc-windows-msvc")]
+    Aarch64PcWindowsMsvc,
+    #[serde(skip)]
     Source,
 }
 
@@ -19,10 +35,57 @@ impl Display for Target {
             Target::X86_64AppleDarwin => write!(f, "x86_64-apple-darwin"),
             Target::X86_64UnknownLinuxGnu => write!(f, "x86_64-unknown-linux-gnu"),
             Target::I686UnknownLinuxGnu => write!(f, "i686-unknown-linux-gnu"),
+            Target::Aarch64UnknownLinuxGnu => write!(f, "aarch64-unknown-linux-gnu"),
             Target::X86_64UnknownLinuxMusl => write!(f, "x86_64-unknown-linux-musl"),
+            Target::Aarch64UnknownLinuxMusl => write!(f, "aarch64-unknown-linux-musl"),
             Target::X86_64PcWindowsMsvc => write!(f, "x86_64-pc-windows-msvc"),
             Target::I686PcWindowsMsvc => write!(f, "i686-pc-windows-msvc"),
+            Target::Aarch64PcWindowsMsvc => write!(f, "aarch64-pc-windows-msvc"),
             Target::Source => write!(f, ""),
         }
     }
 }
+
+impl Target {
+    // Targets built when none are configured
+    pub fn defaults() -> Vec<Target> {
+        vec![
+            Target::X86_64AppleDarwin,
+            Target::Aarch64AppleDarwin,
+            Target::X86_64UnknownLinuxGnu,
+            Target::I686UnknownLinuxGnu,
+            Target::X86_64UnknownLinuxMusl,
+            Target::X86_64PcWindowsMsvc,
+            Target::I686PcWindowsMsvc,
+        ]
+    }
+
+    // GitHub Actions runner which builds the target
+    pub fn runner(&self) -> &'static str {
+        match self {
+            Target::X86_64AppleDarwin => "macos-15-intel",
+            Target::Aarch64AppleDarwin => "macos-latest",
+            Target::Aarch64UnknownLinuxGnu | Target::Aarch64UnknownLinuxMusl => "ubuntu-24.04-arm",
+            Target::X86_64PcWindowsMsvc | Target::I686PcWindowsMsvc => "windows-latest",
+            Target::Aarch64PcWindowsMsvc => "windows-11-arm",
+            _ => "ubuntu-latest",
+        }
+    }
+
+    // Values of `process.platform`, `process.arch` and libc family in node
+    pub fn npm(&self) -> Option<(&'static str, &'static str, Option<&'static str>)> {
+        Some(match self {
+            Target::Aarch64AppleDarwin => ("darwin", "arm64", None),
+            Target::X86_64AppleDarwin => ("darwin", "x64", None),
+            Target::X86_64UnknownLinuxGnu => ("linux", "x64", Some("glibc")),
+            Target::I686UnknownLinuxGnu => ("linux", "ia32", Some("glibc")),
+            Target::Aarch64UnknownLinuxGnu => ("linux", "arm64", Some("glibc")),
+            Target::X86_64UnknownLinuxMusl => ("linux", "x64", Some("musl")),
+            Target::Aarch64UnknownLinuxMusl => ("linux", "arm64", Some("musl")),
+            Target::X86_64PcWindowsMsvc => ("win32", "x64", None),
+            Target::I686PcWindowsMsvc => ("win32", "ia32", None),
+            Target::Aarch64PcWindowsMsvc => ("win32", "arm64", None),
+            Target::Source => return None,
+        })
+    }
+}
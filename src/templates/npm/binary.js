@@ -4,15 +4,50 @@ const path = require("path");
 const zlib = require("zlib");
 const https = require("https");
 
-const { familySync } = require("detect-libc");
-
 const {
   BINARY_DISTRIBUTION_PACKAGES,
+  BINARY_DISTRIBUTION_REGISTRY,
   BINARY_DISTRIBUTION_VERSION,
-  BINARY_NAME,
+  BINARY_NAMES,
 } = require("./constants");
 
-const libc = familySync();
+function detectLibc() {
+  if (process.platform !== "linux") {
+    return null;
+  }
+
+  try {
+    // Prefer detect-libc when it is installed as a dependency
+    return require("detect-libc").familySync();
+  } catch (e) {}
+
+  // Node reports the glibc version it is running against
+  const report = process.report && process.report.getReport();
+
+  if (report && report.header && report.header.glibcVersionRuntime) {
+    return "glibc";
+  }
+
+  try {
+    if (fs.readFileSync("/usr/bin/ldd", "utf-8").includes("musl")) {
+      return "musl";
+    }
+  } catch (e) {}
+
+  try {
+    const output = execFileSync("ldd", ["--version"], {
+      encoding: "utf-8",
+      stdio: ["ignore", "pipe", "pipe"],
+    });
+
+    return output.includes("musl") ? "musl" : "glibc";
+  } catch (e) {
+    // musl's ldd prints its version to stderr and exits with an error
+    return `${e.stderr || ""}`.includes("musl") ? "musl" : null;
+  }
+}
+
+const libc = detectLibc();
 
 const platformSpecificPackageName =
   BINARY_DISTRIBUTION_PACKAGES[
@@ -23,11 +58,21 @@ if (!platformSpecificPackageName) {
   throw new Error("Platform not supported!");
 }
 
+function binaryFileName(binaryName) {
+  return ["win32", "cygwin"].includes(process.platform)
+    ? `${binaryName}.exe`
+    : binaryName;
+}
+
 // Compute the path of packaged binary
-const packagedBinaryPath = `${platformSpecificPackageName}/bin/${BINARY_NAME}`;
+function packagedBinaryPath(binaryName) {
+  return `${platformSpecificPackageName}/bin/${binaryFileName(binaryName)}`;
+}
 
 // Compute the path we want to emit the fallback binary to
-const fallbackBinaryPath = path.join(__dirname, BINARY_NAME);
+function fallbackBinaryPath(binaryName) {
+  return path.join(__dirname, binaryFileName(binaryName));
+}
 
 function makeRequest(url) {
   return new Promise((resolve, reject) => {
@@ -92,23 +137,28 @@ async function downloadBinaryFromNpm() {
 
   // Download the tarball of the right binary distribution package
   const tarballDownloadBuffer = await makeRequest(
-    `https://registry.npmjs.org/${urlName}/-/${name}-${BINARY_DISTRIBUTION_VERSION}.tgz`
+    `${BINARY_DISTRIBUTION_REGISTRY}${urlName}/-/${name}-${BINARY_DISTRIBUTION_VERSION}.tgz`
   );
 
   const tarballBuffer = zlib.unzipSync(tarballDownloadBuffer);
 
-  // Extract binary from package and write to disk
-  fs.writeFileSync(
-    fallbackBinaryPath,
-    extractFileFromTarball(tarballBuffer, `package/bin/${BINARY_NAME}`),
-    { mode: 0o755 } // Make binary file executable
-  );
+  // Extract binaries from package and write to disk
+  for (const binaryName of BINARY_NAMES) {
+    fs.writeFileSync(
+      fallbackBinaryPath(binaryName),
+      extractFileFromTarball(
+        tarballBuffer,
+        `package/bin/${binaryFileName(binaryName)}`
+      ),
+      { mode: 0o755 } // Make binary file executable
+    );
+  }
 }
 
 function isPlatformSpecificPackageInstalled() {
   try {
     // Resolving will fail if the optionalDependency was not installed
-    require.resolve(packagedBinaryPath);
+    require.resolve(packagedBinaryPath(BINARY_NAMES[0]));
     return true;
   } catch (e) {
     return false;
@@ -117,19 +167,21 @@ function isPlatformSpecificPackageInstalled() {
 
 function isBinaryDownloaded() {
   try {
-    // Check if the fallback binary exists
-    return fs.existsSync(fallbackBinaryPath);
+    // Check if the fallback binaries exist
+    return BINARY_NAMES.every((binaryName) =>
+      fs.existsSync(fallbackBinaryPath(binaryName))
+    );
   } catch (e) {
     return false;
   }
 }
 
-function getBinaryPath() {
+function getBinaryPath(binaryName) {
   try {
     // Resolving will fail if the optionalDependency was not installed
-    return require.resolve(packagedBinaryPath);
+    return require.resolve(packagedBinaryPath(binaryName));
   } catch (e) {
-    return fallbackBinaryPath;
+    return fallbackBinaryPath(binaryName);
   }
 }
 
@@ -139,8 +191,12 @@ async function maybeInstall() {
   }
 }
 
-function run() {
-  const binaryPath = getBinaryPath();
+function run(binaryName) {
+  if (!BINARY_NAMES.includes(binaryName)) {
+    throw new Error(`Unknown binary ${binaryName}!`);
+  }
+
+  const binaryPath = getBinaryPath(binaryName);
 
   if (!fs.existsSync(binaryPath)) {
     throw new Error(
@@ -1,5 +1,8 @@
 #!/usr/bin/env node
 
-const { maybeInstall, run } = require("./binary");
+const path = require("path");
 
-maybeInstall().then(() => run());
+const { maybeInstall, run } = require("../binary");
+
+// Each binary gets a copy of this file named after it
+maybeInstall().then(() => run(path.basename(__filename, ".js")));
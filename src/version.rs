@@ -0,0 +1,147 @@
+use eyre::eyre;
+use inquire::Confirm;
+use owo_colors::OwoColorize;
+use reqwest::blocking::Client;
+use semver::Version;
+use tracing::info;
+use xshell::{Shell, cmd};
+
+use crate::{config::AppConfig, error::Result, repositories::Repository, targets::Target};
+
+pub fn is_prerelease(version: &str) -> bool {
+    Version::parse(version).is_ok_and(|v| !v.pre.is_empty())
+}
+
+// Takes the version from `Cargo.toml` or the latest tag pointing at HEAD
+pub fn infer_version(info: &AppConfig, yes: bool) -> Result<String> {
+    let sh = Shell::new()?;
+
+    let tag = || {
+        cmd!(sh, "git tag --points-at HEAD --list v* --sort=-v:refname")
+            .quiet()
+            .ignore_stderr()
+            .read()
+            .ok()
+            .and_then(|tags| {
+                tags.lines()
+                    .next()
+                    .and_then(|tag| tag.strip_prefix('v'))
+                    .map(String::from)
+            })
+    };
+
+    let version = info
+        .cargo_version
+        .clone()
+        .or_else(tag)
+        .ok_or(eyre!("Unable to infer the version, please provide it"))?;
+
+    if !yes {
+        let confirmed = Confirm::new(&format!("Publish version {version}?"))
+            .with_default(true)
+            .prompt()?;
+
+        if !confirmed {
+            return Err(eyre!("Publishing version {version} was not confirmed"));
+        }
+    }
+
+    Ok(version)
+}
+
+// Verifies that the version is ready to be published, reporting all the problems at once
+pub fn validate_version(
+    info: &AppConfig,
+    version: &str,
+    repositories: &[Box<dyn Repository>],
+) -> Result {
+    let AppConfig {
+        name,
+        repository,
+        cargo_version,
+        ..
+    } = info;
+
+    let sh = Shell::new()?;
+    let mut errors = vec![];
+
+    if let Err(e) = Version::parse(version) {
+        errors.push(format!("version {version} is not valid semver: {e}"));
+    }
+
+    if let Some(cargo_version) = cargo_version
+        && cargo_version != version
+    {
+        errors.push(format!(
+            "version {version} does not match the version {cargo_version} in Cargo.toml"
+        ));
+    }
+
+    let tag = format!("v{version}");
+
+    if cmd!(sh, "git rev-parse --verify --quiet refs/tags/{tag}")
+        .quiet()
+        .ignore_stdout()
+        .run()
+        .is_err()
+    {
+        errors.push(format!("tag {tag} does not exist locally"));
+    }
+
+    if cmd!(
+        sh,
+        "git ls-remote --exit-code --tags origin refs/tags/{tag}"
+    )
+    .quiet()
+    .ignore_stdout()
+    .ignore_stderr()
+    .run()
+    .is_err()
+    {
+        errors.push(format!("tag {tag} does not exist on the remote"));
+    }
+
+    let mut targets = vec![];
+
+    for target in repositories.iter().flat_map(|r| r.targets(info)) {
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+
+    let client = Client::new();
+    let download_url =
+        format!("https://github.com/{repository}/releases/download/{tag}/{name}-{tag}");
+
+    for target in targets {
+        let mut urls = vec![];
+
+        if target != Target::Source {
+            urls.push(format!("{download_url}-{target}.zip"));
+            urls.push(format!("{download_url}-{target}_sha256sum.txt"));
+        } else {
+            urls.push(format!("{download_url}_sha256sum.txt"));
+        }
+
+        for url in urls {
+            let reachable = client
+                .head(&url)
+                .send()
+                .is_ok_and(|response| response.status().is_success());
+
+            if !reachable {
+                errors.push(format!("release artifact {url} is not reachable"));
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    for error in &errors {
+        info!("  {} {error}", "fail".red());
+    }
+
+    Err(eyre!("Unable to publish version {version}"))
+}